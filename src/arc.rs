@@ -1,6 +1,8 @@
 use crate::bin_archive::BinArchive;
 use crate::bin_streams::BinArchiveReader;
+use crate::padding::align;
 use crate::{ArcError, Endian};
+use indexmap::IndexMap;
 use std::collections::HashMap;
 
 type Result<T> = std::result::Result<T, ArcError>;
@@ -13,14 +15,42 @@ struct ArcEntry {
     address: u32,
 }
 
+// Size of the header block that precedes file data in padded archives.
+const HEADER_PADDING: u32 = 0x60;
+
+// Each file's data starts on this boundary.
+const FILE_ALIGNMENT: usize = 0x80;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ArcAddressing {
+    // Pick whichever of the other modes keeps every entry inside the archive.
+    Auto,
+    // Entry addresses point directly into the archive's data.
+    Absolute,
+    // Entry addresses are relative to the file data that follows the header padding.
+    Relative,
+}
+
+fn entries_fit(entries: &[ArcEntry], base: u32, size: usize) -> bool {
+    entries
+        .iter()
+        .all(|e| (base as usize + e.address as usize + e.size as usize) <= size)
+}
+
 pub fn from_bytes(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>> {
+    from_bytes_with_addressing(bytes, ArcAddressing::Auto)
+}
+
+pub fn from_bytes_with_addressing(
+    bytes: &[u8],
+    addressing: ArcAddressing,
+) -> Result<HashMap<String, Vec<u8>>> {
     // Read archive and labels.
     let archive = BinArchive::from_bytes(bytes, Endian::Little)?;
     let count_address = archive
         .find_label_address("Count")
         .ok_or(ArcError::NoCount)?;
     let info_address = archive.find_label_address("Info").ok_or(ArcError::NoInfo)?;
-    let header_padding = if archive.read_u32(0)? == 0 { 0x60 } else { 0 };
 
     // Read metadata
     let mut entries: Vec<ArcEntry> = Vec::new();
@@ -31,7 +61,7 @@ pub fn from_bytes(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>> {
         let name = reader.read_string()?.ok_or(ArcError::MissingName)?;
         let index = reader.read_u32()?;
         let size = reader.read_u32()?;
-        let address = reader.read_u32()? + header_padding;
+        let address = reader.read_u32()?;
         entries.push(ArcEntry {
             name,
             index,
@@ -40,20 +70,113 @@ pub fn from_bytes(bytes: &[u8]) -> Result<HashMap<String, Vec<u8>>> {
         });
     }
 
+    let base = match addressing {
+        ArcAddressing::Absolute => 0,
+        ArcAddressing::Relative => HEADER_PADDING,
+        ArcAddressing::Auto => {
+            let padded = archive.read_u32(0)? == 0;
+            if padded && entries_fit(&entries, HEADER_PADDING, archive.size()) {
+                HEADER_PADDING
+            } else if entries_fit(&entries, 0, archive.size()) {
+                0
+            } else if padded {
+                HEADER_PADDING
+            } else {
+                0
+            }
+        }
+    };
+
     // Read files.
     let mut files: HashMap<String, Vec<u8>> = HashMap::new();
     for entry in entries {
-        reader.seek(entry.address as usize);
+        reader.seek(entry.address as usize + base as usize);
         let buffer = reader.read_bytes(entry.size as usize)?;
         files.insert(entry.name, buffer);
     }
     Ok(files)
 }
 
+// Reassigns (index, contents) entries sequential indices in map order.
+// to_bytes numbers entries the same way, so this keeps caller-tracked indices in sync after edits.
+pub fn normalize_indices(files: &mut IndexMap<String, (u32, Vec<u8>)>) {
+    for (index, (entry_index, _)) in files.values_mut().enumerate() {
+        *entry_index = index as u32;
+    }
+}
+
+// Files are written in map order using relative addressing.
+pub fn to_bytes(files: &IndexMap<String, Vec<u8>>) -> Result<Vec<u8>> {
+    let mut archive = BinArchive::new(Endian::Little);
+    archive.allocate_at_end(HEADER_PADDING as usize);
+
+    let mut addresses: Vec<usize> = Vec::new();
+    for contents in files.values() {
+        let address = archive.size();
+        addresses.push(address - HEADER_PADDING as usize);
+        archive.allocate_at_end(align(contents.len(), FILE_ALIGNMENT));
+        archive.write_bytes(address, contents)?;
+    }
+    if !files.is_empty() {
+        archive.write_label(HEADER_PADDING as usize, "Data")?;
+    }
+
+    let count_address = archive.size();
+    archive.allocate_at_end(4 + files.len() * 16);
+    archive.write_label(count_address, "Count")?;
+    archive.write_u32(count_address, files.len() as u32)?;
+    let info_address = count_address + 4;
+    archive.write_label(info_address, "Info")?;
+    for (index, ((name, contents), address)) in files.iter().zip(addresses).enumerate() {
+        let entry_address = info_address + index * 16;
+        archive.write_label(entry_address, name)?;
+        archive.write_string(entry_address, Some(name))?;
+        archive.write_u32(entry_address + 4, index as u32)?;
+        archive.write_u32(entry_address + 8, contents.len() as u32)?;
+        archive.write_u32(entry_address + 12, address as u32)?;
+    }
+    Ok(archive.serialize()?)
+}
+
 #[cfg(test)]
 mod test {
+    use super::*;
     use crate::utils::load_test_file;
 
+    fn build_arc(address: u32) -> Vec<u8> {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(0x80);
+        archive.write_label(4, "Count").unwrap();
+        archive.write_u32(4, 1).unwrap();
+        archive.write_label(8, "Info").unwrap();
+        archive.write_string(8, Some("file")).unwrap();
+        archive.write_u32(0xC, 0).unwrap();
+        archive.write_u32(0x10, 4).unwrap();
+        archive.write_u32(0x14, address).unwrap();
+        archive.write_bytes(0x70, b"ABCD").unwrap();
+        archive.serialize().unwrap()
+    }
+
+    #[test]
+    fn arc_relative_addressing() {
+        let raw_arc = build_arc(0x10);
+        let files = from_bytes_with_addressing(&raw_arc, ArcAddressing::Relative).unwrap();
+        assert_eq!(b"ABCD", files.get("file").unwrap().as_slice());
+        let files = from_bytes(&raw_arc).unwrap();
+        assert_eq!(b"ABCD", files.get("file").unwrap().as_slice());
+    }
+
+    #[test]
+    fn arc_absolute_addressing() {
+        // The header starts with 0, but the address only fits as an absolute one.
+        let raw_arc = build_arc(0x70);
+        let files = from_bytes_with_addressing(&raw_arc, ArcAddressing::Absolute).unwrap();
+        assert_eq!(b"ABCD", files.get("file").unwrap().as_slice());
+        let files = from_bytes(&raw_arc).unwrap();
+        assert_eq!(b"ABCD", files.get("file").unwrap().as_slice());
+        assert!(from_bytes_with_addressing(&raw_arc, ArcAddressing::Relative).is_err());
+    }
+
     #[test]
     fn arc_from_bytes_test() {
         let raw_arc = load_test_file("ArcTest.arc");
@@ -68,4 +191,36 @@ mod test {
         assert_eq!(&test_file_1, files.get("LZ13Test.bin").unwrap());
         assert_eq!(&test_file_2, files.get("LZ13Test.bin.lz").unwrap());
     }
+
+    #[test]
+    fn arc_to_bytes_test() {
+        let raw_arc = load_test_file("ArcTest.arc");
+        let mut files: IndexMap<String, Vec<u8>> = IndexMap::new();
+        files.insert("LZ13Test.bin".to_string(), load_test_file("LZ13Test.bin"));
+        files.insert(
+            "LZ13Test.bin.lz".to_string(),
+            load_test_file("LZ13Test.bin.lz"),
+        );
+        assert_eq!(raw_arc, to_bytes(&files).unwrap());
+    }
+
+    #[test]
+    fn normalize_indices_after_removal() {
+        let mut files: IndexMap<String, (u32, Vec<u8>)> = IndexMap::new();
+        files.insert("a.bin".to_string(), (0, vec![1]));
+        files.insert("b.bin".to_string(), (1, vec![2]));
+        files.insert("c.bin".to_string(), (2, vec![3]));
+        files.shift_remove("a.bin");
+        files.insert("d.bin".to_string(), (7, vec![4]));
+        normalize_indices(&mut files);
+        let indices: Vec<u32> = files.values().map(|(index, _)| *index).collect();
+        assert_eq!(vec![0, 1, 2], indices);
+        assert_eq!(Some(&(1, vec![3])), files.get("c.bin"));
+    }
+
+    #[test]
+    fn arc_to_bytes_empty() {
+        let raw_arc = to_bytes(&IndexMap::new()).unwrap();
+        assert!(from_bytes(&raw_arc).unwrap().is_empty());
+    }
 }
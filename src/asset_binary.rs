@@ -1,4 +1,4 @@
-use crate::{ArchiveError, BinArchive, BinArchiveReader, BinArchiveWriter};
+use crate::{ArchiveError, BinArchive, BinArchiveReader, BinArchiveWriter, FromArchive};
 
 type Result<T> = std::result::Result<T, ArchiveError>;
 
@@ -83,6 +83,15 @@ pub struct AssetSpec {
     pub use_unk13: bool,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssetFieldValue {
+    OptString(Option<String>),
+    Color([u8; 4]),
+    F32(f32),
+    U32(u32),
+    Flag(bool),
+}
+
 fn read_flag_str(
     reader: &mut BinArchiveReader,
     flags: &[u8],
@@ -138,6 +147,180 @@ impl AssetSpec {
         }
     }
 
+    pub fn fields(&self) -> Vec<(&'static str, AssetFieldValue)> {
+        vec![
+            ("name", AssetFieldValue::OptString(self.name.clone())),
+            (
+                "conditional1",
+                AssetFieldValue::OptString(self.conditional1.clone()),
+            ),
+            (
+                "conditional2",
+                AssetFieldValue::OptString(self.conditional2.clone()),
+            ),
+            (
+                "body_model",
+                AssetFieldValue::OptString(self.body_model.clone()),
+            ),
+            (
+                "body_texture",
+                AssetFieldValue::OptString(self.body_texture.clone()),
+            ),
+            (
+                "head_model",
+                AssetFieldValue::OptString(self.head_model.clone()),
+            ),
+            (
+                "head_texture",
+                AssetFieldValue::OptString(self.head_texture.clone()),
+            ),
+            (
+                "hair_model",
+                AssetFieldValue::OptString(self.hair_model.clone()),
+            ),
+            (
+                "hair_texture",
+                AssetFieldValue::OptString(self.hair_texture.clone()),
+            ),
+            (
+                "outer_clothing_model",
+                AssetFieldValue::OptString(self.outer_clothing_model.clone()),
+            ),
+            (
+                "outer_clothing_texture",
+                AssetFieldValue::OptString(self.outer_clothing_texture.clone()),
+            ),
+            (
+                "underwear_model",
+                AssetFieldValue::OptString(self.underwear_model.clone()),
+            ),
+            (
+                "underwear_texture",
+                AssetFieldValue::OptString(self.underwear_texture.clone()),
+            ),
+            (
+                "mount_model",
+                AssetFieldValue::OptString(self.mount_model.clone()),
+            ),
+            (
+                "mount_texture",
+                AssetFieldValue::OptString(self.mount_texture.clone()),
+            ),
+            (
+                "mount_outer_clothing_model",
+                AssetFieldValue::OptString(self.mount_outer_clothing_model.clone()),
+            ),
+            (
+                "mount_outer_clothing_texture",
+                AssetFieldValue::OptString(self.mount_outer_clothing_texture.clone()),
+            ),
+            (
+                "weapon_model_dual",
+                AssetFieldValue::OptString(self.weapon_model_dual.clone()),
+            ),
+            (
+                "weapon_model",
+                AssetFieldValue::OptString(self.weapon_model.clone()),
+            ),
+            (
+                "skeleton",
+                AssetFieldValue::OptString(self.skeleton.clone()),
+            ),
+            (
+                "mount_skeleton",
+                AssetFieldValue::OptString(self.mount_skeleton.clone()),
+            ),
+            (
+                "accessory1_model",
+                AssetFieldValue::OptString(self.accessory1_model.clone()),
+            ),
+            (
+                "accessory1_texture",
+                AssetFieldValue::OptString(self.accessory1_texture.clone()),
+            ),
+            (
+                "accessory2_model",
+                AssetFieldValue::OptString(self.accessory2_model.clone()),
+            ),
+            (
+                "accessory2_texture",
+                AssetFieldValue::OptString(self.accessory2_texture.clone()),
+            ),
+            (
+                "accessory3_model",
+                AssetFieldValue::OptString(self.accessory3_model.clone()),
+            ),
+            (
+                "accessory3_texture",
+                AssetFieldValue::OptString(self.accessory3_texture.clone()),
+            ),
+            (
+                "attack_animation",
+                AssetFieldValue::OptString(self.attack_animation.clone()),
+            ),
+            (
+                "attack_animation2",
+                AssetFieldValue::OptString(self.attack_animation2.clone()),
+            ),
+            (
+                "visual_effect",
+                AssetFieldValue::OptString(self.visual_effect.clone()),
+            ),
+            ("hid", AssetFieldValue::OptString(self.hid.clone())),
+            (
+                "footstep_sound",
+                AssetFieldValue::OptString(self.footstep_sound.clone()),
+            ),
+            (
+                "clothing_sound",
+                AssetFieldValue::OptString(self.clothing_sound.clone()),
+            ),
+            ("voice", AssetFieldValue::OptString(self.voice.clone())),
+            ("hair_color", AssetFieldValue::Color(self.hair_color)),
+            ("use_hair_color", AssetFieldValue::Flag(self.use_hair_color)),
+            ("skin_color", AssetFieldValue::Color(self.skin_color)),
+            ("use_skin_color", AssetFieldValue::Flag(self.use_skin_color)),
+            (
+                "weapon_trail_color",
+                AssetFieldValue::Color(self.weapon_trail_color),
+            ),
+            (
+                "use_weapon_trail_color",
+                AssetFieldValue::Flag(self.use_weapon_trail_color),
+            ),
+            ("model_size", AssetFieldValue::F32(self.model_size)),
+            ("use_model_size", AssetFieldValue::Flag(self.use_model_size)),
+            ("head_size", AssetFieldValue::F32(self.head_size)),
+            ("use_head_size", AssetFieldValue::Flag(self.use_head_size)),
+            ("pupil_y", AssetFieldValue::F32(self.pupil_y)),
+            ("use_pupil_y", AssetFieldValue::Flag(self.use_pupil_y)),
+            ("unk3", AssetFieldValue::U32(self.unk3)),
+            ("use_unk3", AssetFieldValue::Flag(self.use_unk3)),
+            ("unk4", AssetFieldValue::U32(self.unk4)),
+            ("use_unk4", AssetFieldValue::Flag(self.use_unk4)),
+            ("unk5", AssetFieldValue::U32(self.unk5)),
+            ("use_unk5", AssetFieldValue::Flag(self.use_unk5)),
+            ("unk6", AssetFieldValue::U32(self.unk6)),
+            ("use_unk6", AssetFieldValue::Flag(self.use_unk6)),
+            ("bitflags", AssetFieldValue::Color(self.bitflags)),
+            ("use_bitflags", AssetFieldValue::Flag(self.use_bitflags)),
+            ("unk7", AssetFieldValue::U32(self.unk7)),
+            ("use_unk7", AssetFieldValue::Flag(self.use_unk7)),
+            ("unk8", AssetFieldValue::U32(self.unk8)),
+            ("use_unk8", AssetFieldValue::Flag(self.use_unk8)),
+            ("unk9", AssetFieldValue::U32(self.unk9)),
+            ("use_unk9", AssetFieldValue::Flag(self.use_unk9)),
+            ("unk10", AssetFieldValue::U32(self.unk10)),
+            ("use_unk10", AssetFieldValue::Flag(self.use_unk10)),
+            ("unk11", AssetFieldValue::U32(self.unk11)),
+            ("use_unk11", AssetFieldValue::Flag(self.use_unk11)),
+            ("unk12", AssetFieldValue::U32(self.unk12)),
+            ("use_unk12", AssetFieldValue::Flag(self.use_unk12)),
+            ("unk13", AssetFieldValue::U32(self.unk13)),
+            ("use_unk13", AssetFieldValue::Flag(self.use_unk13)),
+        ]
+    }
+
     pub fn from_stream(reader: &mut BinArchiveReader) -> Result<Self> {
         let mut flag_count = 3;
         let raw = reader.read_u8()?;
@@ -605,11 +788,35 @@ impl AssetBinary {
     }
 }
 
+impl FromArchive for AssetBinary {
+    fn from_archive(archive: &BinArchive) -> Result<Self> {
+        AssetBinary::from_archive(archive)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use crate::{utils::load_test_file, Endian};
 
+    #[test]
+    fn fields() {
+        let mut spec = AssetSpec::new();
+        spec.name = Some("PID_Test".to_string());
+        spec.model_size = 1.5;
+        let fields = spec.fields();
+        assert_eq!(70, fields.len());
+        assert_eq!(
+            (
+                "name",
+                AssetFieldValue::OptString(Some("PID_Test".to_string()))
+            ),
+            fields[0]
+        );
+        assert!(fields.contains(&("model_size", AssetFieldValue::F32(1.5))));
+        assert_eq!(("use_unk13", AssetFieldValue::Flag(false)), fields[69]);
+    }
+
     #[test]
     fn round_trip() {
         let file = load_test_file("AssetBinary_Test.bin");
@@ -1,6 +1,7 @@
-use crate::texture::Texture;
-use crate::texture_decoder;
-use crate::TextureParseError;
+use crate::texture::{Texture, TextureSourceFormat};
+use crate::texture_decoder::{self, HardwareTextureFormat};
+use crate::texture_utils;
+use crate::{TextureContainer, TextureParseError, TextureWriteOptions};
 use byteorder::{LittleEndian, ReadBytesExt};
 use encoding_rs::UTF_8;
 use std::io::prelude::*;
@@ -8,6 +9,9 @@ use std::io::{Cursor, Read, Seek, SeekFrom};
 
 type Result<T> = std::result::Result<T, TextureParseError>;
 
+// BCH is a 3DS format, so everything is little endian.
+type FileEndian = LittleEndian;
+
 #[allow(dead_code)]
 pub struct Header {
     magic_id: u32,
@@ -32,35 +36,35 @@ pub struct Header {
 
 impl Header {
     fn new(reader: &mut Cursor<&[u8]>) -> Result<Self> {
-        let magic_id = reader.read_u32::<LittleEndian>()?;
+        let magic_id = reader.read_u32::<FileEndian>()?;
         if magic_id != 0x484342 {
             return Err(TextureParseError::BadMagicNumber);
         }
         let backward_compatibility = reader.read_u8()?;
         let forward_compatibility = reader.read_u8()?;
-        let version = reader.read_u16::<LittleEndian>()?;
-        let contents_address = reader.read_u32::<LittleEndian>()?;
-        let strings_address = reader.read_u32::<LittleEndian>()?;
-        let commands_address = reader.read_u32::<LittleEndian>()?;
-        let raw_data_address = reader.read_u32::<LittleEndian>()?;
+        let version = reader.read_u16::<FileEndian>()?;
+        let contents_address = reader.read_u32::<FileEndian>()?;
+        let strings_address = reader.read_u32::<FileEndian>()?;
+        let commands_address = reader.read_u32::<FileEndian>()?;
+        let raw_data_address = reader.read_u32::<FileEndian>()?;
         let raw_ext_address = if backward_compatibility > 20 {
-            reader.read_u32::<LittleEndian>()?
+            reader.read_u32::<FileEndian>()?
         } else {
             0
         };
-        let relocation_address = reader.read_u32::<LittleEndian>()?;
-        let contents_length = reader.read_u32::<LittleEndian>()?;
-        let strings_length = reader.read_u32::<LittleEndian>()?;
-        let commands_length = reader.read_u32::<LittleEndian>()?;
-        let raw_data_length = reader.read_u32::<LittleEndian>()?;
+        let relocation_address = reader.read_u32::<FileEndian>()?;
+        let contents_length = reader.read_u32::<FileEndian>()?;
+        let strings_length = reader.read_u32::<FileEndian>()?;
+        let commands_length = reader.read_u32::<FileEndian>()?;
+        let raw_data_length = reader.read_u32::<FileEndian>()?;
         let raw_ext_length = if backward_compatibility > 20 {
-            reader.read_u32::<LittleEndian>()?
+            reader.read_u32::<FileEndian>()?
         } else {
             0
         };
-        let relocation_length = reader.read_u32::<LittleEndian>()?;
-        let uninit_data_length = reader.read_u32::<LittleEndian>()?;
-        let uninit_commands_length = reader.read_u32::<LittleEndian>()?;
+        let relocation_length = reader.read_u32::<FileEndian>()?;
+        let uninit_data_length = reader.read_u32::<FileEndian>()?;
+        let uninit_commands_length = reader.read_u32::<FileEndian>()?;
 
         Ok(Header {
             magic_id,
@@ -92,8 +96,8 @@ pub struct ContentTable {
 impl ContentTable {
     fn new(reader: &mut Cursor<&[u8]>, contents_address: u32) -> Result<Self> {
         reader.seek(SeekFrom::Start((contents_address + 0x24).into()))?;
-        let textures_ptr_table_offset = reader.read_u32::<LittleEndian>()? + contents_address;
-        let textures_ptr_table_entries = reader.read_u32::<LittleEndian>()?;
+        let textures_ptr_table_offset = reader.read_u32::<FileEndian>()? + contents_address;
+        let textures_ptr_table_entries = reader.read_u32::<FileEndian>()?;
 
         Ok(ContentTable {
             textures_ptr_table_offset,
@@ -102,66 +106,234 @@ impl ContentTable {
     }
 }
 
-pub fn read(file: &[u8]) -> Result<Vec<Texture>> {
-    let mut reader = Cursor::new(file);
+pub fn texture_count(file: &[u8]) -> Result<usize> {
+    Ok(read_header(file)?.texture_count)
+}
+
+// Header fields that are useful without decoding anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicHeader {
+    pub backward_compatibility: u8,
+    pub forward_compatibility: u8,
+    pub version: u16,
+    pub texture_count: usize,
+    pub contents_length: u32,
+    pub strings_length: u32,
+    pub commands_length: u32,
+    pub raw_data_length: u32,
+    pub raw_ext_length: u32,
+    pub relocation_length: u32,
+}
 
+pub fn read_header(file: &[u8]) -> Result<PublicHeader> {
+    let mut reader = Cursor::new(file);
     let header = Header::new(&mut reader)?;
+    let content_table = ContentTable::new(&mut reader, header.contents_address)?;
+    Ok(PublicHeader {
+        backward_compatibility: header.backward_compatibility,
+        forward_compatibility: header.forward_compatibility,
+        version: header.version,
+        texture_count: content_table.textures_ptr_table_entries as usize,
+        contents_length: header.contents_length,
+        strings_length: header.strings_length,
+        commands_length: header.commands_length,
+        raw_data_length: header.raw_data_length,
+        raw_ext_length: header.raw_ext_length,
+        relocation_length: header.relocation_length,
+    })
+}
+
+struct TextureEntry {
+    filename: String,
+    width: usize,
+    height: usize,
+    data_offset: u32,
+    pixel_format: HardwareTextureFormat,
+}
+
+fn read_texture_entry(
+    reader: &mut Cursor<&[u8]>,
+    header: &Header,
+    content_table: &ContentTable,
+    entry: u32,
+) -> Result<TextureEntry> {
+    reader.seek(SeekFrom::Start(
+        (content_table.textures_ptr_table_offset + entry * 4).into(),
+    ))?;
 
-    reader.seek(SeekFrom::Start(header.contents_address.into()))?;
+    let dest = reader.read_u32::<FileEndian>()?;
+    reader.seek(SeekFrom::Start((dest + header.contents_address).into()))?;
+
+    let tex_unit0_commands_offset = reader.read_u32::<FileEndian>()? + header.commands_address;
+    reader.seek(SeekFrom::Current(24))?;
+
+    let name_offset = reader.read_u32::<FileEndian>()?;
+
+    // Read filename
+    reader.seek(SeekFrom::Start(
+        (header.strings_address + name_offset).into(),
+    ))?;
+    let mut filename_buffer: Vec<u8> = Vec::new();
+    reader.read_until(0x0, &mut filename_buffer)?;
+    filename_buffer.pop(); // Get rid of the null terminator.
+    let (result, _, errors) = UTF_8.decode(filename_buffer.as_slice());
+    if errors {
+        return Err(TextureParseError::BadText);
+    }
+    let filename: String = result.into();
+
+    reader.seek(SeekFrom::Start(tex_unit0_commands_offset.into()))?;
+    let height = reader.read_u16::<FileEndian>()? as usize;
+    let width = reader.read_u16::<FileEndian>()? as usize;
+    reader.seek(SeekFrom::Current(0xC))?;
+    let data_offset = reader.read_u32::<FileEndian>()? + header.raw_data_address;
+    reader.seek(SeekFrom::Current(0x4))?;
+    let raw_format = reader.read_u32::<FileEndian>()?;
+    let pixel_format = HardwareTextureFormat::from_u32(raw_format)
+        .ok_or(TextureParseError::UnknownPixelFormat(raw_format))?;
+    Ok(TextureEntry {
+        filename,
+        width,
+        height,
+        data_offset,
+        pixel_format,
+    })
+}
+
+fn read_level(
+    reader: &mut Cursor<&[u8]>,
+    entry: &TextureEntry,
+    width: usize,
+    height: usize,
+    offset: usize,
+) -> Result<Texture> {
+    reader.seek(SeekFrom::Start(entry.data_offset as u64 + offset as u64))?;
+    let mut raw: Vec<u8> =
+        vec![0; texture_decoder::get_level_size(width, height, entry.pixel_format)];
+    reader.read_exact(&mut raw)?;
+    let pixel_data = texture_decoder::decode_pixel_data(&raw, width, height, entry.pixel_format)?;
+    Ok(Texture {
+        filename: entry.filename.clone(),
+        height,
+        width,
+        pixel_data,
+        raw,
+        source_format: Some(TextureSourceFormat::Pica(entry.pixel_format)),
+    })
+}
+
+// Textures are decoded one at a time as the iterator advances.
+pub fn iter_textures(file: &[u8]) -> impl Iterator<Item = Result<Texture>> + '_ {
+    let mut reader = Cursor::new(file);
+    let tables = Header::new(&mut reader).and_then(|header| {
+        let content_table = ContentTable::new(&mut reader, header.contents_address)?;
+        Ok((header, content_table))
+    });
+    let (tables, error) = match tables {
+        Ok(tables) => (Some(tables), None),
+        Err(err) => (None, Some(Err(err))),
+    };
+    let count = tables.as_ref().map_or(0, |(_, content_table)| {
+        content_table.textures_ptr_table_entries
+    });
+    error.into_iter().chain((0..count).map(move |entry| {
+        // Only reachable when the tables parsed, since count is 0 otherwise.
+        let (header, content_table) = tables.as_ref().unwrap();
+        let entry = read_texture_entry(&mut reader, header, content_table, entry)?;
+        read_level(&mut reader, &entry, entry.width, entry.height, 0)
+    }))
+}
+
+pub fn read(file: &[u8]) -> Result<Vec<Texture>> {
+    iter_textures(file).collect()
+}
+
+pub fn read_lenient(file: &[u8]) -> (Vec<Texture>, Vec<TextureParseError>) {
+    texture_utils::collect_lenient(iter_textures(file))
+}
+
+pub fn read_mip(file: &[u8], texture_index: usize, level: usize) -> Result<Texture> {
+    let mut reader = Cursor::new(file);
+    let header = Header::new(&mut reader)?;
     let content_table = ContentTable::new(&mut reader, header.contents_address)?;
+    if texture_index >= content_table.textures_ptr_table_entries as usize {
+        return Err(TextureParseError::TextureIndexOutOfRange(texture_index));
+    }
+    let entry = read_texture_entry(&mut reader, &header, &content_table, texture_index as u32)?;
+
+    // BCH doesn't give us a reliable level count, so stop once a level drops below one tile.
+    let width = entry.width.checked_shr(level as u32).unwrap_or(0);
+    let height = entry.height.checked_shr(level as u32).unwrap_or(0);
+    if width < 8 || height < 8 {
+        return Err(TextureParseError::MipLevelOutOfRange(level));
+    }
+    let offset =
+        texture_decoder::get_mip_offset(entry.width, entry.height, entry.pixel_format, level);
+    read_level(&mut reader, &entry, width, height, offset)
+}
 
-    let mut bch: Vec<Texture> = Vec::new();
+pub struct Bch;
 
-    for entry in 0..content_table.textures_ptr_table_entries {
-        reader.seek(SeekFrom::Start(
-            (content_table.textures_ptr_table_offset + entry * 4).into(),
-        ))?;
+impl TextureContainer for Bch {
+    fn read(bytes: &[u8]) -> Result<Vec<Texture>> {
+        read(bytes)
+    }
 
-        let dest = reader.read_u32::<LittleEndian>()?;
-        reader.seek(SeekFrom::Start((dest + header.contents_address).into()))?;
+    fn write(_textures: &[Texture], _options: &TextureWriteOptions) -> Result<Vec<u8>> {
+        Err(TextureParseError::WriteNotSupported("BCH".to_string()))
+    }
+}
 
-        let tex_unit0_commands_offset =
-            reader.read_u32::<LittleEndian>()? + header.commands_address;
-        reader.seek(SeekFrom::Current(24))?;
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::load_test_file;
 
-        let name_offset = reader.read_u32::<LittleEndian>()?;
+    #[test]
+    fn height_before_width() {
+        let file = load_test_file("Bch_Test.bin");
+        assert_eq!(1, texture_count(&file).unwrap());
+        let textures = read(&file).unwrap();
+        assert_eq!("tex", textures[0].filename);
+        assert_eq!(16, textures[0].width);
+        assert_eq!(8, textures[0].height);
+        assert_eq!(16 * 8 * 4, textures[0].pixel_data.len());
+    }
 
-        // Read filename
-        reader.seek(SeekFrom::Start(
-            (header.strings_address + name_offset).into(),
-        ))?;
-        let mut filename_buffer: Vec<u8> = Vec::new();
-        reader.read_until(0x0, &mut filename_buffer)?;
-        filename_buffer.pop(); // Get rid of the null terminator.
-        let (result, _, errors) = UTF_8.decode(filename_buffer.as_slice());
-        if errors {
-            return Err(TextureParseError::BadText);
-        }
-        let filename: String = result.into();
-
-        reader.seek(SeekFrom::Start(tex_unit0_commands_offset.into()))?;
-        let height = reader.read_u16::<LittleEndian>()? as usize;
-        let width = reader.read_u16::<LittleEndian>()? as usize;
-        reader.seek(SeekFrom::Current(0xC))?;
-        let data_offset = reader.read_u32::<LittleEndian>()? + header.raw_data_address;
-        reader.seek(SeekFrom::Current(0x4))?;
-        let pixel_format = reader.read_u32::<LittleEndian>()?;
-
-        reader.seek(SeekFrom::Start(data_offset.into()))?;
-        let mut pixel_data: Vec<u8> = vec![
-            0;
-            (texture_decoder::get_pixel_format_bpp(pixel_format) * width as f32 * height as f32)
-                as usize
-        ];
-        reader.read_exact(&mut pixel_data)?;
-        let pixel_data =
-            texture_decoder::decode_pixel_data(&pixel_data, width, height, pixel_format)?;
-        bch.push(Texture {
-            filename,
-            height,
-            width,
-            pixel_data,
-        });
+    #[test]
+    fn read_header() {
+        let mut file = load_test_file("Bch_Test.bin");
+        file[0x6..0x8].copy_from_slice(&0x21u16.to_le_bytes());
+        file[0x20..0x24].copy_from_slice(&0x60u32.to_le_bytes());
+        let header = super::read_header(&file).unwrap();
+        assert_eq!(0x14, header.backward_compatibility);
+        assert_eq!(0x21, header.version);
+        assert_eq!(1, header.texture_count);
+        assert_eq!(0x60, header.strings_length);
+        assert_eq!(0, header.raw_ext_length);
+        assert!(super::read_header(&[0; 0x40]).is_err());
+    }
+
+    #[test]
+    fn read_lenient() {
+        let file = load_test_file("Bch_Test.bin");
+        let (textures, errors) = super::read_lenient(&file);
+        assert_eq!(1, textures.len());
+        assert!(errors.is_empty());
+
+        // Cut off the end of the pixel data.
+        let (textures, errors) = super::read_lenient(&file[..file.len() - 0x10]);
+        assert!(textures.is_empty());
+        assert_eq!(1, errors.len());
+    }
+
+    #[test]
+    fn iter_textures_bad_magic() {
+        let mut iter = iter_textures(&[0; 0x40]);
+        assert!(matches!(
+            iter.next(),
+            Some(Err(TextureParseError::BadMagicNumber))
+        ));
+        assert!(iter.next().is_none());
     }
-    Ok(bch)
 }
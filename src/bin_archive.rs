@@ -1,5 +1,6 @@
 use crate::encoded_strings::{to_shift_jis, EncodedStringReader};
-use crate::errors::ArchiveError;
+use crate::errors::{ArchiveError, EncodedStringsError};
+use crate::padding::{align, checked_align, pad_to};
 use crate::{Endian, EndianAwareReader, EndianAwareWriter};
 use encoding_rs::SHIFT_JIS;
 use indexmap::IndexMap;
@@ -8,16 +9,65 @@ use std::io::{Cursor, Read, Seek, SeekFrom, Write};
 
 type Result<T> = std::result::Result<T, ArchiveError>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct BinArchive {
     data: Vec<u8>,
     text: HashMap<usize, String>,
     pointers: HashMap<usize, usize>,
     labels: HashMap<usize, Vec<String>>,
     cstrings: HashMap<String, Vec<usize>>,
+    trailer: Vec<u8>,
+    // Pointer table sources in the order from_bytes read them.
+    pointer_order: Vec<usize>,
     endian: Endian,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct RemovedMetadata {
+    pub address: usize,
+    pub bytes: Vec<u8>,
+    pub text: HashMap<usize, String>,
+    pub pointers: HashMap<usize, usize>,
+    pub labels: HashMap<usize, Vec<String>>,
+}
+
+// Suspicious pointers found by BinArchive::validate_and_report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArchiveWarning {
+    DestinationOutOfBounds {
+        address: usize,
+        destination: usize,
+    },
+    UnalignedDestination {
+        address: usize,
+        destination: usize,
+    },
+    // Destination is unlabeled but a label sits one cell away. Older tools produce these.
+    OffByFour {
+        address: usize,
+        destination: usize,
+        label_address: usize,
+    },
+}
+
+// Header, pointer table and labels of an archive, without its data section.
+// Addresses are relative to the data section, same as BinArchive.
+#[derive(Debug, Clone)]
+pub struct ArchiveIndex {
+    pub endian: Endian,
+    pub data_size: usize,
+    pub pointers: Vec<usize>,
+    pub labels: Vec<(usize, String)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveDump {
+    pub data: Vec<u8>,
+    pub pointers: Vec<(usize, usize)>,
+    pub labels: Vec<(usize, String)>,
+    pub texts: Vec<(usize, String)>,
+}
+
 fn validate_address(address: usize, size: usize, end_is_valid: bool) -> Result<()> {
     if (end_is_valid && address > size) || (!end_is_valid && address >= size) {
         Err(ArchiveError::OutOfBoundsAddress(address, size))
@@ -34,6 +84,17 @@ fn validate_alignment(value: usize, bytes: usize) -> Result<()> {
     }
 }
 
+// Returns the string and the position just past its terminator.
+fn read_text(bytes: &[u8], address: usize) -> Result<(String, usize)> {
+    if address >= bytes.len() {
+        return Err(EncodedStringsError::UnterminatedString.into());
+    }
+    let mut cursor = Cursor::new(bytes);
+    cursor.set_position(address as u64);
+    let string = cursor.read_shift_jis_string()?;
+    Ok((string, cursor.position() as usize))
+}
+
 fn add_text(
     raw_text: &mut Vec<u8>,
     raw_text_offsets: &mut HashMap<String, usize>,
@@ -51,6 +112,31 @@ fn add_text(
     }
 }
 
+// Offset of the text section relative to the end of the header.
+// None if a crafted header's counts would overflow.
+fn text_section_start(data_size: usize, pointer_count: usize, label_count: usize) -> Option<usize> {
+    checked_align(data_size, 4)?
+        .checked_add(pointer_count.checked_mul(4)?)?
+        .checked_add(label_count.checked_mul(8)?)
+}
+
+fn read_header_file_size(bytes: &[u8], endian: Endian) -> Option<usize> {
+    if bytes.len() < 0x20 {
+        return None;
+    }
+    let mut cursor = Cursor::new(bytes);
+    let file_size = cursor.read_u32(endian).ok()? as usize;
+    let data_size = cursor.read_u32(endian).ok()? as usize;
+    let pointer_count = cursor.read_u32(endian).ok()? as usize;
+    let label_count = cursor.read_u32(endian).ok()? as usize;
+    let text_start = text_section_start(data_size, pointer_count, label_count)?;
+    if text_start.checked_add(0x20)? > bytes.len() {
+        None
+    } else {
+        Some(file_size)
+    }
+}
+
 fn adjust_pointer(pointer: usize, address: usize, count: usize, subtract: bool) -> usize {
     if pointer >= address {
         if subtract {
@@ -157,6 +243,8 @@ impl BinArchive {
             pointers: HashMap::new(),
             labels: HashMap::new(),
             cstrings: HashMap::new(),
+            trailer: Vec::new(),
+            pointer_order: Vec::new(),
             endian,
         }
     }
@@ -214,27 +302,42 @@ impl BinArchive {
             return Err(ArchiveError::ArchiveTooSmall);
         }
         let mut cursor = Cursor::new(bytes);
-        cursor.set_position(4);
+        let file_size = cursor.read_u32(endian)? as usize;
         let data_size = cursor.read_u32(endian)?;
         let pointer_count = cursor.read_u32(endian)?;
         let label_count = cursor.read_u32(endian)?;
-        let text_start = (data_size + (pointer_count * 4) + (label_count * 8)) as usize;
-        if text_start + 0x20 > bytes.len() {
-            return Err(ArchiveError::ArchiveTooSmall);
-        }
+        let text_start = text_section_start(
+            data_size as usize,
+            pointer_count as usize,
+            label_count as usize,
+        )
+        .filter(|start| start.saturating_add(0x20) <= bytes.len())
+        .ok_or(ArchiveError::ArchiveTooSmall)?;
+
+        // Strings have to terminate before the end of the file given by the header.
+        // Fall back to the buffer's end if the header size is obviously wrong.
+        let text_limit = if (text_start + 0x20..=bytes.len()).contains(&file_size) {
+            file_size
+        } else {
+            bytes.len()
+        };
+        let text_bytes = &bytes[..text_limit];
 
         let mut archive = BinArchive::new(endian);
+        let mut text_end = text_start + 0x20;
         cursor.seek(SeekFrom::Start(0x20))?;
         archive.data.resize(data_size as usize, 0);
         cursor.read_exact(&mut archive.data)?;
+        cursor.seek(SeekFrom::Start(
+            (align(data_size as usize, 4) + 0x20) as u64,
+        ))?;
         for _ in 0..pointer_count {
             let pointer_address = cursor.read_u32(endian)? as usize;
+            archive.pointer_order.push(pointer_address);
             let pointer_value = archive.read_u32(pointer_address)? as usize;
             if pointer_value > data_size as usize {
-                let original_position = cursor.position();
-                cursor.seek(SeekFrom::Start((pointer_value + 0x20) as u64))?;
-                let string = cursor.read_shift_jis_string()?;
-                cursor.seek(SeekFrom::Start(original_position))?;
+                let (string, end) = read_text(text_bytes, pointer_value.saturating_add(0x20))?;
+                text_end = text_end.max(end);
                 archive.write_string(pointer_address, Some(&string))?;
             } else {
                 archive.write_pointer(pointer_address, Some(pointer_value))?;
@@ -244,16 +347,92 @@ impl BinArchive {
         for _ in 0..label_count {
             let address = cursor.read_u32(endian)?;
             let offset = cursor.read_u32(endian)? as usize;
-            let text_address = text_start + offset + 0x20;
-            let original_position = cursor.position();
-            cursor.seek(SeekFrom::Start(text_address as u64))?;
-            let string = cursor.read_shift_jis_string()?;
-            cursor.seek(SeekFrom::Start(original_position))?;
+            let (string, end) = read_text(text_bytes, (text_start + 0x20).saturating_add(offset))?;
+            text_end = text_end.max(end);
             archive.write_label(address as usize, &string)?;
         }
+
+        // Some files carry a footer after the text section. Keep it so it can be written back.
+        if text_end < bytes.len() {
+            archive.trailer = bytes[text_end..].to_vec();
+        }
         Ok(archive)
     }
 
+    // Parses only the metadata tables so huge archives can be scanned without copying their data.
+    // Labels are sorted by address.
+    pub fn index_only(bytes: &[u8], endian: Endian) -> Result<ArchiveIndex> {
+        if bytes.len() < 0x20 {
+            return Err(ArchiveError::ArchiveTooSmall);
+        }
+        let mut cursor = Cursor::new(bytes);
+        cursor.seek(SeekFrom::Start(4))?;
+        let data_size = cursor.read_u32(endian)? as usize;
+        let pointer_count = cursor.read_u32(endian)? as usize;
+        let label_count = cursor.read_u32(endian)? as usize;
+        let text_start = text_section_start(data_size, pointer_count, label_count)
+            .filter(|start| start.saturating_add(0x20) <= bytes.len())
+            .ok_or(ArchiveError::ArchiveTooSmall)?;
+
+        let mut index = ArchiveIndex {
+            endian,
+            data_size,
+            pointers: Vec::with_capacity(pointer_count),
+            labels: Vec::with_capacity(label_count),
+        };
+        let pointers_start = align(data_size, 4) + 0x20;
+        cursor.seek(SeekFrom::Start(pointers_start as u64))?;
+        for _ in 0..pointer_count {
+            index.pointers.push(cursor.read_u32(endian)? as usize);
+        }
+        for _ in 0..label_count {
+            let address = cursor.read_u32(endian)? as usize;
+            let offset = cursor.read_u32(endian)? as usize;
+            let (label, _) = read_text(bytes, (text_start + 0x20).saturating_add(offset))?;
+            index.labels.push((address, label));
+        }
+        index.labels.sort();
+        Ok(index)
+    }
+
+    // Slices the data from a label up to the next labeled address (or the end of the data).
+    pub fn read_record<'a>(
+        bytes: &'a [u8],
+        index: &ArchiveIndex,
+        label: &str,
+    ) -> Result<Option<&'a [u8]>> {
+        let start = match index.labels.iter().find(|(_, l)| l == label) {
+            Some((address, _)) => *address,
+            None => return Ok(None),
+        };
+        let end = index
+            .labels
+            .iter()
+            .map(|(address, _)| *address)
+            .find(|address| *address > start)
+            .unwrap_or(index.data_size);
+        validate_address(start, end, true)?;
+        validate_address(end, index.data_size, true)?;
+        validate_address(start + 0x20, bytes.len(), true)?;
+        validate_address(end + 0x20, bytes.len(), true)?;
+        Ok(Some(&bytes[start + 0x20..end + 0x20]))
+    }
+
+    pub fn detect_endian(bytes: &[u8]) -> Option<Endian> {
+        let candidates: Vec<(Endian, usize)> = [Endian::Little, Endian::Big]
+            .iter()
+            .filter_map(|endian| read_header_file_size(bytes, *endian).map(|size| (*endian, size)))
+            .collect();
+
+        // Both interpretations can fit for small archives, so prefer the one whose
+        // header agrees with the actual file size.
+        candidates
+            .iter()
+            .find(|(_, file_size)| *file_size == bytes.len())
+            .or_else(|| candidates.first())
+            .map(|(endian, _)| *endian)
+    }
+
     pub fn get_labels(&self) -> Vec<(usize, String)> {
         let mut keys: Vec<(usize, String)> = Vec::new();
         for (k, v) in &self.labels {
@@ -269,7 +448,69 @@ impl BinArchive {
         self.data.len()
     }
 
+    pub fn trailer(&self) -> &[u8] {
+        &self.trailer
+    }
+
+    pub fn set_trailer(&mut self, trailer: Vec<u8>) {
+        self.trailer = trailer;
+    }
+
+    // Like serialize, but independent of the order labels and c-string references were added in.
+    // Useful for comparing or hashing archives. The output may not match the original file.
+    pub fn canonical_bytes(&self) -> Result<Vec<u8>> {
+        let mut archive = self.clone();
+        for bucket in archive.labels.values_mut() {
+            bucket.sort();
+        }
+        for addresses in archive.cstrings.values_mut() {
+            addresses.sort_unstable();
+        }
+        archive.serialize()
+    }
+
     pub fn serialize(&self) -> Result<Vec<u8>> {
+        self.serialize_internal(false)
+    }
+
+    // The length serialize would produce, computed without laying out the file.
+    pub fn serialized_size(&self) -> Result<usize> {
+        let text_values: HashSet<&String> = self.text.values().collect();
+        let mut cstrings_size = 0;
+        let mut pointer_count = self.pointers.len() + self.text.len();
+        for (text, addresses) in &self.cstrings {
+            pointer_count += addresses.len();
+            cstrings_size += to_shift_jis(text)?.len() + 1;
+        }
+        let data_size = self.data.len() + align(cstrings_size, 4);
+
+        // Text is deduplicated across labels and strings.
+        let mut strings: HashSet<&String> = text_values;
+        let mut label_count = 0;
+        for bucket in self.labels.values() {
+            label_count += bucket.len();
+            strings.extend(bucket.iter());
+        }
+        let mut text_size = 0;
+        for string in strings {
+            text_size += to_shift_jis(string)?.len() + 1;
+        }
+
+        Ok(align(data_size, 4)
+            + pointer_count * 4
+            + label_count * 8
+            + text_size
+            + self.trailer.len()
+            + 0x20)
+    }
+
+    // Like serialize, but pointers that were read by from_bytes are written in their original
+    // table order instead of being sorted. Anything added since then goes after them.
+    pub fn serialize_preserving_order(&self) -> Result<Vec<u8>> {
+        self.serialize_internal(true)
+    }
+
+    fn serialize_internal(&self, preserve_pointer_order: bool) -> Result<Vec<u8>> {
         let mut data = self.data.clone();
         let mut raw_pointers: Vec<u32> = Vec::new();
         let mut raw_labels: Vec<u32> = Vec::new();
@@ -279,7 +520,6 @@ impl BinArchive {
 
         let mut pointers: Vec<(usize, usize)> = self.pointers.clone().into_iter().collect();
         let mut cstrings: Vec<(&String, &Vec<usize>)> = self.cstrings.iter().collect();
-        let mut labels: Vec<(&usize, &Vec<String>)> = self.labels.iter().collect();
         let mut text: Vec<(&usize, &String)> = self.text.iter().collect();
 
         let mut raw_cstrings: Vec<u8> = Vec::new();
@@ -289,7 +529,8 @@ impl BinArchive {
             let text_b = b.0;
             let (bytes_a, _, _) = SHIFT_JIS.encode(text_a);
             let (bytes_b, _, _) = SHIFT_JIS.encode(text_b);
-            bytes_a.cmp(&bytes_b)
+            // Unencodable strings can produce the same bytes, so fall back to the text itself.
+            bytes_a.cmp(&bytes_b).then_with(|| text_a.cmp(text_b))
         });
         for (text, addresses) in cstrings {
             let offset = add_text(&mut raw_cstrings, &mut offset_tracker, text)?;
@@ -298,9 +539,7 @@ impl BinArchive {
                 pointers.push((*address, text_address));
             }
         }
-        while raw_cstrings.len() % 4 != 0 {
-            raw_cstrings.push(0);
-        }
+        pad_to(&mut raw_cstrings, 4);
 
         pointers.sort_by(|a, b| a.0.cmp(&b.0));
         for (source, destination) in pointers {
@@ -309,25 +548,32 @@ impl BinArchive {
             raw_pointers.push(source as u32);
         }
 
+        // Labels are written individually so buckets with several labels stay in
+        // the order from_bytes reads them back in. The sorts are stable, so labels
+        // that share an address keep their bucket order.
+        let mut labels: Vec<(&usize, &String)> = self
+            .labels
+            .iter()
+            .flat_map(|(address, bucket)| bucket.iter().map(move |label| (address, label)))
+            .collect();
         if let Endian::Big = self.endian {
-            labels.sort_by(|a, b| a.1.cmp(b.1));
+            labels.sort_by(|a, b| a.1.cmp(b.1).then_with(|| a.0.cmp(b.0)));
         } else {
             labels.sort_by(|a, b| a.0.cmp(b.0));
         }
-        
-        for (address, bucket) in labels {
-            for label in bucket {
-                let offset = add_text(&mut raw_text, &mut raw_text_offsets, label)?;
-                raw_labels.push(*address as u32);
-                raw_labels.push(offset as u32);
-            }
+
+        for (address, label) in labels {
+            let offset = add_text(&mut raw_text, &mut raw_text_offsets, label)?;
+            raw_labels.push(*address as u32);
+            raw_labels.push(offset as u32);
         }
 
         
         text.sort_by(|a, b| a.0.cmp(b.0));
         let mut ptr_data_pairs: IndexMap<usize, Vec<u32>> = IndexMap::new();
+        let data_size = self.data.len() + raw_cstrings.len();
         let text_start =
-            self.data.len() + (self.pointers.len() + self.text.len() + raw_labels.len()) * 4;
+            align(data_size, 4) + (raw_pointers.len() + self.text.len() + raw_labels.len()) * 4;
         for (address, string) in text {
             let offset = add_text(&mut raw_text, &mut raw_text_offsets, string)?;
             let text_address = text_start + offset;
@@ -348,23 +594,38 @@ impl BinArchive {
                 raw_pointers.push(ptr);
             }
         }
+        if preserve_pointer_order {
+            let positions: HashMap<usize, usize> = self
+                .pointer_order
+                .iter()
+                .enumerate()
+                .map(|(position, source)| (*source, position))
+                .collect();
+            raw_pointers.sort_by_key(|source| {
+                positions
+                    .get(&(*source as usize))
+                    .copied()
+                    .unwrap_or(usize::MAX)
+            });
+        }
 
         let mut bytes: Vec<u8> = Vec::new();
-        let file_size = self.data.len()
-            + raw_cstrings.len()
+        let file_size = align(data_size, 4)
             + (raw_pointers.len() * 4)
             + (raw_labels.len() * 4)
             + raw_text.len()
+            + self.trailer.len()
             + 0x20;
         bytes.resize(file_size, 0);
         let mut cursor: Cursor<&mut [u8]> = Cursor::new(&mut bytes);
         cursor.write_u32(file_size as u32, self.endian)?;
-        cursor.write_u32(data.len() as u32 + raw_cstrings.len() as u32, self.endian)?;
+        cursor.write_u32(data_size as u32, self.endian)?;
         cursor.write_u32(raw_pointers.len() as u32, self.endian)?;
         cursor.write_u32((raw_labels.len() / 2) as u32, self.endian)?;
         cursor.seek(SeekFrom::Start(0x20))?;
         cursor.write_all(&data)?;
         cursor.write_all(&raw_cstrings)?;
+        cursor.seek(SeekFrom::Start((align(data_size, 4) + 0x20) as u64))?;
         for pointer in raw_pointers {
             cursor.write_u32(pointer, self.endian)?;
         }
@@ -372,6 +633,7 @@ impl BinArchive {
             cursor.write_u32(label_part, self.endian)?;
         }
         cursor.write_all(&raw_text)?;
+        cursor.write_all(&self.trailer)?;
         Ok(bytes)
     }
 
@@ -421,12 +683,26 @@ impl BinArchive {
         Ok(&self.data[address..(address + amount)])
     }
 
+    // Parses the raw bytes at [address, address + len) as a standalone archive.
+    pub fn extract_sub_archive(&self, address: usize, len: usize) -> Result<BinArchive> {
+        BinArchive::from_bytes(self.read_bytes(address, len)?, self.endian)
+    }
+
     pub fn read_string(&self, address: usize) -> Result<Option<String>> {
         validate_address(address, self.size(), false)?;
         validate_address(address + 4, self.size(), true)?;
         Ok(self.text.get(&address).map(|x| x.to_owned()))
     }
 
+    // Whether new encodes to no more bytes than the string currently at address.
+    // Returns false if there's no string to replace.
+    pub fn string_fits(&self, address: usize, new: &str) -> Result<bool> {
+        match self.read_string(address)? {
+            Some(old) => Ok(to_shift_jis(new)?.len() <= to_shift_jis(&old)?.len()),
+            None => Ok(false),
+        }
+    }
+
     pub fn read_pointer(&self, address: usize) -> Result<Option<usize>> {
         validate_address(address, self.size(), false)?;
         validate_address(address + 4, self.size(), true)?;
@@ -451,6 +727,23 @@ impl BinArchive {
         }
     }
 
+    // Non-erroring presence checks. Out of range addresses are treated as empty cells.
+    fn cell_in_range(&self, address: usize) -> bool {
+        address.checked_add(4).is_some_and(|end| end <= self.size())
+    }
+
+    pub fn has_pointer(&self, address: usize) -> bool {
+        self.cell_in_range(address) && self.pointers.contains_key(&address)
+    }
+
+    pub fn has_string(&self, address: usize) -> bool {
+        self.cell_in_range(address) && self.text.contains_key(&address)
+    }
+
+    pub fn has_labels(&self, address: usize) -> bool {
+        self.cell_in_range(address) && self.labels.contains_key(&address)
+    }
+
     pub fn delete_string(&mut self, address: usize) -> Result<()> {
         validate_address(address, self.size(), false)?;
         validate_address(address + 4, self.size(), true)?;
@@ -618,15 +911,52 @@ impl BinArchive {
         self.text = new_text;
         self.labels = new_labels;
         self.pointers = new_pointers;
+        for source in &mut self.pointer_order {
+            *source = adjust_pointer(*source, address, amount_in_bytes, false);
+        }
         Ok(())
     }
 
     pub fn deallocate(&mut self, address: usize, amount_in_bytes: usize, ge: bool) -> Result<()> {
+        self.deallocate_returning(address, amount_in_bytes, ge)?;
+        Ok(())
+    }
+
+    pub fn deallocate_returning(
+        &mut self,
+        address: usize,
+        amount_in_bytes: usize,
+        ge: bool,
+    ) -> Result<RemovedMetadata> {
         validate_address(address, self.size(), false)?;
         validate_address(address + amount_in_bytes, self.size(), true)?;
         validate_alignment(address, 4)?;
         validate_alignment(amount_in_bytes, 4)?;
-        self.data.drain(address..(address + amount_in_bytes));
+        let range = address..(address + amount_in_bytes);
+        let removed = RemovedMetadata {
+            address,
+            bytes: self.data.drain(range.clone()).collect(),
+            text: self
+                .text
+                .iter()
+                .filter(|(addr, _)| range.contains(addr))
+                .map(|(addr, value)| (*addr, value.clone()))
+                .collect(),
+            pointers: self
+                .pointers
+                .iter()
+                .filter(|(source, destination)| {
+                    range.contains(source) || range.contains(destination)
+                })
+                .map(|(source, destination)| (*source, *destination))
+                .collect(),
+            labels: self
+                .labels
+                .iter()
+                .filter(|(addr, _)| range.contains(addr))
+                .map(|(addr, value)| (*addr, value.clone()))
+                .collect(),
+        };
         let filtered_text = filter_text_or_labels(&self.text, address, amount_in_bytes);
         let filtered_labels = filter_text_or_labels(&self.labels, address, amount_in_bytes);
         let filtered_pointers = filter_pointers(&self.pointers, address, amount_in_bytes);
@@ -636,15 +966,80 @@ impl BinArchive {
         self.text = new_text;
         self.labels = new_labels;
         self.pointers = new_pointers;
+        self.pointer_order.retain(|source| !range.contains(source));
+        for source in &mut self.pointer_order {
+            *source = adjust_pointer(*source, address, amount_in_bytes, true);
+        }
+        Ok(removed)
+    }
+
+    // Zeroes [address, address + amount_in_bytes) without shifting anything. Pointers and
+    // strings whose 4 byte cells sit entirely inside the region are dropped, as are labels
+    // on addresses inside it. Pointers from outside into the region are left alone.
+    pub fn clear_region(&mut self, address: usize, amount_in_bytes: usize) -> Result<()> {
+        validate_address(address, self.size(), false)?;
+        validate_address(address + amount_in_bytes, self.size(), true)?;
+        let end = address + amount_in_bytes;
+        let cell_inside = |cell: &usize| *cell >= address && *cell + 4 <= end;
+        self.data[address..end].iter_mut().for_each(|b| *b = 0);
+        self.text.retain(|cell, _| !cell_inside(cell));
+        self.pointers.retain(|cell, _| !cell_inside(cell));
+        self.pointer_order.retain(|cell| !cell_inside(cell));
+        for addresses in self.cstrings.values_mut() {
+            addresses.retain(|cell| !cell_inside(cell));
+        }
+        self.cstrings.retain(|_, addresses| !addresses.is_empty());
+        self.labels
+            .retain(|label_address, _| !(address..end).contains(label_address));
         Ok(())
     }
 
+    // Appends other's data and metadata, shifting everything by the returned base address.
+    // The base is 4 byte aligned so other's pointers and labels stay aligned.
+    pub fn append_archive(&mut self, other: &BinArchive) -> Result<usize> {
+        if self.endian != other.endian {
+            return Err(ArchiveError::OtherError(
+                "Cannot append an archive with a different endian.".to_string(),
+            ));
+        }
+        if !other.trailer.is_empty() {
+            return Err(ArchiveError::OtherError(
+                "Cannot append an archive with a trailer.".to_string(),
+            ));
+        }
+        pad_to(&mut self.data, 4);
+        let base = self.size();
+        self.data.extend_from_slice(&other.data);
+        for (address, text) in &other.text {
+            self.text.insert(address + base, text.clone());
+        }
+        for (source, destination) in &other.pointers {
+            self.pointers.insert(source + base, destination + base);
+        }
+        for (address, bucket) in &other.labels {
+            self.labels
+                .entry(address + base)
+                .or_default()
+                .extend(bucket.iter().cloned());
+        }
+        for (text, addresses) in &other.cstrings {
+            self.cstrings
+                .entry(text.clone())
+                .or_default()
+                .extend(addresses.iter().map(|address| address + base));
+        }
+        self.pointer_order
+            .extend(other.pointer_order.iter().map(|source| source + base));
+        Ok(base)
+    }
+
     pub fn truncate(&mut self, address: usize) -> Result<()> {
         if address >= self.data.len() {
             return Ok(());
         }
         let range = address..self.data.len();
         self.data.drain(range.clone());
+        self.pointer_order.retain(|source| *source < address);
         for i in range.step_by(4) {
             self.text.remove(&i);
             self.labels.remove(&i);
@@ -664,6 +1059,112 @@ impl BinArchive {
         None
     }
 
+    // Flags pointers whose destinations don't look like the start of a section.
+    // This doesn't modify anything, it's meant for warning users about files from buggy tools.
+    pub fn validate_and_report(&self) -> Vec<ArchiveWarning> {
+        let mut addresses: Vec<usize> = self.pointers.keys().copied().collect();
+        addresses.sort_unstable();
+
+        let mut warnings = Vec::new();
+        for address in addresses {
+            let destination = self.pointers[&address];
+            if destination > self.size() {
+                warnings.push(ArchiveWarning::DestinationOutOfBounds {
+                    address,
+                    destination,
+                });
+            } else if !destination.is_multiple_of(4) && !self.starts_c_string(destination) {
+                warnings.push(ArchiveWarning::UnalignedDestination {
+                    address,
+                    destination,
+                });
+            } else if !self.labels.contains_key(&destination) {
+                let neighbors = [destination.checked_sub(4), destination.checked_add(4)];
+                let label_address = neighbors
+                    .iter()
+                    .flatten()
+                    .find(|neighbor| self.labels.contains_key(neighbor));
+                if let Some(label_address) = label_address {
+                    warnings.push(ArchiveWarning::OffByFour {
+                        address,
+                        destination,
+                        label_address: *label_address,
+                    });
+                }
+            }
+        }
+        warnings
+    }
+
+    // C-strings are packed back to back in the data section, so their destinations
+    // don't need to be aligned. A destination counts if it starts a NUL-terminated string.
+    fn starts_c_string(&self, destination: usize) -> bool {
+        let starts_string = destination == 0 || self.data.get(destination - 1) == Some(&0);
+        match self.data.get(destination..) {
+            Some([first, rest @ ..]) => starts_string && *first != 0 && rest.contains(&0),
+            _ => false,
+        }
+    }
+
+    // 4-aligned cells with no string, pointer, c-string or label. These hold plain data.
+    pub fn unclaimed_cells(&self) -> Vec<usize> {
+        let cstring_cells: HashSet<usize> = self.cstrings.values().flatten().copied().collect();
+        (0..self.size() / 4)
+            .map(|cell| cell * 4)
+            .filter(|address| {
+                !self.text.contains_key(address)
+                    && !self.pointers.contains_key(address)
+                    && !self.labels.contains_key(address)
+                    && !cstring_cells.contains(address)
+            })
+            .collect()
+    }
+
+    // Label -> address map for repeated lookups. A label used at several addresses
+    // maps to the lowest one.
+    pub fn build_label_index(&self) -> HashMap<String, usize> {
+        let mut index: HashMap<String, usize> = HashMap::new();
+        for (address, bucket) in &self.labels {
+            for label in bucket {
+                index
+                    .entry(label.clone())
+                    .and_modify(|existing| *existing = (*existing).min(*address))
+                    .or_insert(*address);
+            }
+        }
+        index
+    }
+
+    pub fn read_u32_from_label(&self, label: &str, offset: usize) -> Result<u32> {
+        let address = self
+            .find_label_address(label)
+            .ok_or_else(|| ArchiveError::MissingLabel(label.to_string()))?;
+        self.read_u32(address + offset)
+    }
+
+    pub fn pointers_into_range(&self, address: usize, len: usize) -> Vec<(usize, usize)> {
+        let range = address..(address + len);
+        let mut result: Vec<(usize, usize)> = self
+            .pointers
+            .iter()
+            .filter(|(_, destination)| range.contains(destination))
+            .map(|(source, destination)| (*source, *destination))
+            .collect();
+        result.sort_unstable();
+        result
+    }
+
+    pub fn repoint(&mut self, old_destination: usize, new_destination: usize) -> usize {
+        let mut count = 0;
+        for destination in self.pointers.values_mut() {
+            if *destination == old_destination {
+                *destination = new_destination;
+                count += 1;
+            }
+        }
+        count
+    }
+
     pub fn pointer_destinations(&self) -> HashSet<usize> {
         self.pointers.values().copied().collect()
     }
@@ -678,13 +1179,35 @@ impl BinArchive {
         result.sort_by(|a, b| a.0.cmp(&b.0));
         result
     }
+
+    pub fn pointers(&self) -> Vec<(usize, usize)> {
+        let mut result: Vec<(usize, usize)> = self.pointers.iter().map(|(k, v)| (*k, *v)).collect();
+        result.sort_unstable();
+        result
+    }
+
+    pub fn texts(&self) -> Vec<(usize, String)> {
+        let mut result: Vec<(usize, String)> =
+            self.text.iter().map(|(k, v)| (*k, v.clone())).collect();
+        result.sort_unstable();
+        result
+    }
+
+    pub fn dump(&self) -> ArchiveDump {
+        ArchiveDump {
+            data: self.data.clone(),
+            pointers: self.pointers(),
+            labels: self.all_labels(),
+            texts: self.texts(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::BinArchive;
+    use super::{ArchiveWarning, BinArchive};
     use crate::utils::load_test_file;
-    use crate::Endian;
+    use crate::{ArchiveError, EncodedStringsError, Endian};
     use maplit::hashmap;
     use std::collections::{HashMap, HashSet};
 
@@ -692,11 +1215,7 @@ mod tests {
     fn size() {
         let archive = BinArchive {
             data: vec![0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let archive2 = BinArchive::new(Endian::Little);
         assert_eq!(archive.size(), 4);
@@ -718,8 +1237,7 @@ mod tests {
                 0 => vec!["Assessment".to_string()],
                 4 => labels.clone()
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let other = BinArchive {
             data: vec![0, 0, 0, 0, 5, 0, 0, 1, 4, 12, 0, 1, 16, 12, 0, 2],
@@ -733,8 +1251,7 @@ mod tests {
                 4 => vec!["Assessment".to_string()],
                 8 => labels.clone()
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
 
         assert!(source.assert_equal_regions(&other, 0, 4, 12).is_ok());
@@ -744,19 +1261,11 @@ mod tests {
     fn assert_equal_regions_bytes_mismatch() {
         let source = BinArchive {
             data: vec![0, 0, 0, 0, 12, 1, 8, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let other = BinArchive {
             data: vec![12, 1, 7, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
 
         assert!(source.assert_equal_regions(&other, 4, 0, 4).is_err());
@@ -766,21 +1275,14 @@ mod tests {
     fn assert_equal_regions_pointer_mismatch() {
         let source = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let other = BinArchive {
             data: vec![0, 0, 0, 0],
-            text: HashMap::new(),
             pointers: hashmap! {
                 0 => 4
             },
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
 
         assert!(source.assert_equal_regions(&other, 4, 0, 4).is_err());
@@ -793,20 +1295,14 @@ mod tests {
             text: hashmap! {
                 4 => "Test".to_string()
             },
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let other = BinArchive {
             data: vec![0, 0, 0, 0],
             text: hashmap! {
                 0 => "Exam".to_string()
             },
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
 
         assert!(source.assert_equal_regions(&other, 4, 0, 4).is_err());
@@ -816,23 +1312,17 @@ mod tests {
     fn assert_equal_regions_label_mismatch() {
         let source = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 4 => vec!["Severa".to_string()]
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let other = BinArchive {
             data: vec![0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 0 => vec!["Selena".to_string()]
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
 
         assert!(source.assert_equal_regions(&other, 4, 0, 4).is_err());
@@ -843,14 +1333,11 @@ mod tests {
         let labels = vec!["Owain".to_string(), "Severa".to_string()];
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 0 => vec!["Test".to_string()],
                 4 => labels.clone()
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let labels = archive.get_labels();
         assert_eq!(
@@ -867,11 +1354,7 @@ mod tests {
     fn read_f32() {
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0x3F, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_f32(4);
         let result2 = archive.read_f32(8);
@@ -884,11 +1367,7 @@ mod tests {
     fn read_u8() {
         let archive = BinArchive {
             data: vec![0, 23],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_u8(1);
         let result2 = archive.read_u8(2);
@@ -901,11 +1380,7 @@ mod tests {
     fn read_u16() {
         let archive = BinArchive {
             data: vec![0, 0, 0x14, 0xFE, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_u16(2);
         let result2 = archive.read_u16(8);
@@ -920,11 +1395,7 @@ mod tests {
     fn read_u32() {
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0x14, 0xFE, 0x15, 0xFE, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_u32(4);
         let result2 = archive.read_u32(8);
@@ -937,11 +1408,7 @@ mod tests {
     fn read_i8() {
         let archive = BinArchive {
             data: vec![0, 23],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_i8(1);
         let result2 = archive.read_i8(2);
@@ -954,11 +1421,7 @@ mod tests {
     fn read_i16() {
         let archive = BinArchive {
             data: vec![0, 0, 0x12, 0x11, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_i16(2);
         let result2 = archive.read_i16(8);
@@ -973,11 +1436,7 @@ mod tests {
     fn read_i32() {
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0x14, 0x11, 0x15, 0x11, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_u32(4);
         let result2 = archive.read_u32(8);
@@ -990,11 +1449,7 @@ mod tests {
     fn read_bytes() {
         let archive = BinArchive {
             data: vec![0, 0x14, 0x11, 0x15, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected1: Vec<u8> = vec![0x14, 0x11, 0x15];
         let result1 = archive.read_bytes(1, 3);
@@ -1013,10 +1468,7 @@ mod tests {
             text: hashmap! {
                 4 => "test".to_string()
             },
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_string(4);
         let result2 = archive.read_string(8);
@@ -1031,13 +1483,10 @@ mod tests {
     fn read_pointer() {
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
             pointers: hashmap! {
                 4 => 0
             },
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_pointer(4);
         let result2 = archive.read_pointer(8);
@@ -1057,13 +1506,10 @@ mod tests {
         ];
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 4 => labels.clone()
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.read_labels(4);
         let result2 = archive.read_labels(8);
@@ -1078,13 +1524,10 @@ mod tests {
     fn read_c_string() {
         let archive = BinArchive {
             data: vec![0, 0, 0, 0x4, 0x41, 0x42, 0x43, 0x0, 0x0, 0x0, 0x0, 0x0],
-            text: HashMap::new(),
             pointers: hashmap! {
                 0 => 4,
             },
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Big,
+            ..BinArchive::new(Endian::Big)
         };
         let expected = Some(String::from("ABC"));
         let result1 = archive.read_c_string(0);
@@ -1104,10 +1547,7 @@ mod tests {
             text: hashmap! {
                 4 => "test".to_string()
             },
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, String> = HashMap::new();
         let result1 = archive.delete_string(4);
@@ -1123,13 +1563,10 @@ mod tests {
     fn delete_pointer() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
             pointers: hashmap! {
                 4 => 0
             },
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, usize> = HashMap::new();
         let result1 = archive.delete_pointer(4);
@@ -1150,13 +1587,10 @@ mod tests {
         ];
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 4 => labels.clone()
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, Vec<String>> = HashMap::new();
         let result1 = archive.delete_labels(4);
@@ -1177,13 +1611,10 @@ mod tests {
         ];
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 4 => labels.clone()
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, Vec<String>> = hashmap! {
             4 => vec!["Owain".to_string(), "Inigo".to_string()]
@@ -1199,11 +1630,7 @@ mod tests {
     fn write_f32() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0, 0, 0, 0, 0, 0, 0x3F, 0];
         let result1 = archive.write_f32(4, 0.5);
@@ -1219,11 +1646,7 @@ mod tests {
     fn write_u8() {
         let mut archive = BinArchive {
             data: vec![0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0x23];
         let result1 = archive.write_u8(1, 0x23);
@@ -1237,11 +1660,7 @@ mod tests {
     fn write_u16() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0, 0x12, 0x11, 0];
         let result1 = archive.write_u16(2, 0x1112);
@@ -1257,11 +1676,7 @@ mod tests {
     fn write_u32() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0, 0, 0, 0x12, 0x11, 0x22, 0x23, 0];
         let result1 = archive.write_u32(4, 0x23221112);
@@ -1275,11 +1690,7 @@ mod tests {
     fn write_i8() {
         let mut archive = BinArchive {
             data: vec![0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0x23];
         let result1 = archive.write_i8(1, 0x23);
@@ -1293,11 +1704,7 @@ mod tests {
     fn write_i16() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0, 0x12, 0x11, 0];
         let result1 = archive.write_i16(2, 0x1112);
@@ -1313,11 +1720,7 @@ mod tests {
     fn write_i32() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0, 0, 0, 0x12, 0x11, 0x22, 0x23, 0];
         let result1 = archive.write_i32(4, 0x23221112);
@@ -1332,11 +1735,7 @@ mod tests {
         let bytes: Vec<u8> = vec![0xFE, 0xFF];
         let mut archive = BinArchive {
             data: vec![0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0xFE, 0xFF];
         let result1 = archive.write_bytes(1, &bytes);
@@ -1352,11 +1751,7 @@ mod tests {
     fn write_string() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, String> = hashmap! {
             4 => "test".to_string()
@@ -1372,11 +1767,7 @@ mod tests {
     fn write_pointer() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, usize> = hashmap! {
             4 => 0
@@ -1397,11 +1788,7 @@ mod tests {
         ];
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, Vec<String>> = hashmap! {
             4 => labels.clone(),
@@ -1424,13 +1811,10 @@ mod tests {
         ];
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 4 => labels1
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: HashMap<usize, Vec<String>> = hashmap! {
             0 => vec!["test".to_string()],
@@ -1454,14 +1838,11 @@ mod tests {
         ];
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 0 => vec!["test".to_string()],
                 4 => labels
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let search1 = archive.find_label_address("Selena");
         let search2 = archive.find_label_address("Severa");
@@ -1477,15 +1858,12 @@ mod tests {
     fn pointer_destinations() {
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
             pointers: hashmap! {
                 4 => 0,
                 0 => 4,
                 8 => 0
             },
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
 
         let mut expected = HashSet::new();
@@ -1494,6 +1872,57 @@ mod tests {
         assert_eq!(archive.pointer_destinations(), expected);
     }
 
+    #[test]
+    fn pointers_into_range() {
+        let archive = BinArchive {
+            data: vec![0; 16],
+            pointers: hashmap! {
+                0 => 8,
+                4 => 12,
+                8 => 4,
+                12 => 9
+            },
+            ..BinArchive::new(Endian::Little)
+        };
+        assert_eq!(vec![(0, 8), (12, 9)], archive.pointers_into_range(8, 4));
+        assert!(archive.pointers_into_range(0, 4).is_empty());
+    }
+
+    #[test]
+    fn read_u32_from_label() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(12);
+        archive.write_label(4, "Record").unwrap();
+        archive.write_u32(8, 0x1234).unwrap();
+        assert_eq!(0x1234, archive.read_u32_from_label("Record", 4).unwrap());
+        assert!(matches!(
+            archive.read_u32_from_label("Missing", 0),
+            Err(ArchiveError::MissingLabel(_))
+        ));
+        assert!(archive.read_u32_from_label("Record", 8).is_err());
+    }
+
+    #[test]
+    fn repoint() {
+        let mut archive = BinArchive {
+            data: vec![0; 16],
+            pointers: hashmap! {
+                0 => 8,
+                4 => 12,
+                8 => 8
+            },
+            ..BinArchive::new(Endian::Little)
+        };
+        assert_eq!(2, archive.repoint(8, 4));
+        assert_eq!(0, archive.repoint(8, 4));
+        let expected = hashmap! {
+            0 => 4,
+            4 => 12,
+            8 => 4
+        };
+        assert_eq!(expected, archive.pointers);
+    }
+
     #[test]
     fn all_labels() {
         let expected: Vec<(usize, String)> = vec![
@@ -1504,8 +1933,6 @@ mod tests {
         ];
         let archive = BinArchive {
             data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
             labels: hashmap! {
                 0 => vec!["test".to_string()],
                 4 => vec![
@@ -1514,21 +1941,43 @@ mod tests {
                 ],
                 8 => vec!["Selena".to_string()]
             },
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         assert_eq!(archive.all_labels(), expected);
     }
 
+    #[test]
+    fn dump() {
+        let archive = BinArchive {
+            data: vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            text: hashmap! {
+                8 => "Text".to_string(),
+                4 => "Other".to_string()
+            },
+            pointers: hashmap! {
+                4 => 0,
+                0 => 8
+            },
+            labels: hashmap! {
+                0 => vec!["Label".to_string()]
+            },
+            ..BinArchive::new(Endian::Little)
+        };
+        let dump = archive.dump();
+        assert_eq!(archive.data, dump.data);
+        assert_eq!(vec![(0, 8), (4, 0)], dump.pointers);
+        assert_eq!(vec![(0, "Label".to_string())], dump.labels);
+        assert_eq!(
+            vec![(4, "Other".to_string()), (8, "Text".to_string())],
+            dump.texts
+        );
+    }
+
     #[test]
     fn allocate_at_end() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let expected: Vec<u8> = vec![0, 0, 0, 0, 0, 0, 0, 0];
         archive.allocate_at_end(4);
@@ -1540,11 +1989,7 @@ mod tests {
     fn allocate_validation() {
         let mut archive = BinArchive {
             data: vec![0, 0, 0, 0],
-            text: HashMap::new(),
-            pointers: HashMap::new(),
-            labels: HashMap::new(),
-            cstrings: HashMap::new(),
-            endian: Endian::Little,
+            ..BinArchive::new(Endian::Little)
         };
         let result1 = archive.allocate(2, 4, false);
         let result2 = archive.allocate(0, 3, false);
@@ -1574,6 +2019,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deallocate_returning() {
+        let mut archive = BinArchive {
+            data: vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0],
+            text: hashmap! {
+                4 => "Text".to_string(),
+                12 => "Kept".to_string()
+            },
+            pointers: hashmap! {
+                0 => 8,
+                12 => 0
+            },
+            labels: hashmap! {
+                8 => vec!["Label".to_string()]
+            },
+            ..BinArchive::new(Endian::Little)
+        };
+        let removed = archive.deallocate_returning(4, 8, false).unwrap();
+        assert_eq!(4, removed.address);
+        assert_eq!(vec![2, 0, 0, 0, 3, 0, 0, 0], removed.bytes);
+        assert_eq!(hashmap! { 4 => "Text".to_string() }, removed.text);
+        assert_eq!(hashmap! { 0 => 8 }, removed.pointers);
+        assert_eq!(hashmap! { 8 => vec!["Label".to_string()] }, removed.labels);
+        assert_eq!(vec![1, 0, 0, 0, 4, 0, 0, 0], archive.data);
+        assert_eq!(hashmap! { 4 => "Kept".to_string() }, archive.text);
+        assert_eq!(hashmap! { 4 => 0 }, archive.pointers);
+        assert!(archive.labels.is_empty());
+    }
+
     #[test]
     fn allocate_no_label_shift() {
         let bytes = load_test_file("Allocate_NoLabelShift.bin");
@@ -1605,16 +2079,439 @@ mod tests {
         test_archive_for_error("ArchiveTest_BadInternalPointer.bin");
     }
 
+    #[test]
+    fn from_bytes_unterminated_string() {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend(0x2Eu32.to_le_bytes());
+        bytes.extend(4u32.to_le_bytes());
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        bytes.resize(0x20, 0);
+        bytes.extend(8u32.to_le_bytes());
+        bytes.extend(0u32.to_le_bytes());
+        bytes.extend(b"Hello\0");
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(Some("Hello".to_string()), archive.read_string(0).unwrap());
+
+        // The string runs past the end of the file according to the header.
+        bytes[0] = 0x2B;
+        assert!(matches!(
+            BinArchive::from_bytes(&bytes, Endian::Little),
+            Err(ArchiveError::EncodingStringsError(
+                EncodedStringsError::UnterminatedString
+            ))
+        ));
+    }
+
     #[test]
     fn from_bytes_bad_size() {
         test_archive_for_error("ArchiveTest_BadSize.bin");
     }
 
+    #[test]
+    fn detect_endian() {
+        let little = load_test_file("ArchiveTest_Mixed1.bin");
+        let big = load_test_file("TextArchive_Legacy_Test.bin");
+        assert!(matches!(
+            BinArchive::detect_endian(&little),
+            Some(Endian::Little)
+        ));
+        assert!(matches!(BinArchive::detect_endian(&big), Some(Endian::Big)));
+        assert!(BinArchive::detect_endian(&[0; 0x10]).is_none());
+    }
+
+    #[test]
+    fn serialize_shares_identical_c_strings() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(12);
+        archive.write_string(0, Some("Shared")).unwrap();
+        archive.write_c_string(4, "Shared".to_string()).unwrap();
+        archive.write_c_string(8, "Shared".to_string()).unwrap();
+
+        // One copy in the data section for both c-strings, one in the text section.
+        let bytes = archive.serialize().unwrap();
+        let occurrences = bytes.windows(6).filter(|w| w == b"Shared").count();
+        assert_eq!(2, occurrences);
+        assert_eq!(bytes.len(), archive.serialized_size().unwrap());
+
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(Some("Shared".to_string()), archive.read_string(0).unwrap());
+        assert_eq!(
+            Some("Shared".to_string()),
+            archive.read_c_string(4).unwrap()
+        );
+        assert_eq!(
+            Some("Shared".to_string()),
+            archive.read_c_string(8).unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_bytes_ignores_insertion_order() {
+        let mut first = BinArchive::new(Endian::Little);
+        first.allocate_at_end(16);
+        first.write_label(0, "B").unwrap();
+        first.write_label(0, "A").unwrap();
+        first.write_label(4, "C").unwrap();
+        first.write_c_string(8, "Test".to_string()).unwrap();
+        first.write_c_string(12, "Test".to_string()).unwrap();
+
+        let mut second = BinArchive::new(Endian::Little);
+        second.allocate_at_end(16);
+        second.write_label(4, "C").unwrap();
+        second.write_label(0, "A").unwrap();
+        second.write_label(0, "B").unwrap();
+        second.write_c_string(12, "Test".to_string()).unwrap();
+        second.write_c_string(8, "Test".to_string()).unwrap();
+
+        assert_ne!(first.serialize().unwrap(), second.serialize().unwrap());
+        assert_eq!(
+            first.canonical_bytes().unwrap(),
+            second.canonical_bytes().unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip_trailer() {
+        let mut bytes = load_test_file("ArchiveTest_Mixed1.bin");
+        bytes.extend(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        let size = bytes.len() as u32;
+        bytes[0..4].copy_from_slice(&size.to_le_bytes());
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(&[0xDE, 0xAD, 0xBE, 0xEF], archive.trailer());
+        assert_eq!(bytes, archive.serialize().unwrap());
+    }
+
     #[test]
     fn round_trip_only_text() {
         test_archive_for_success("ArchiveTest_OnlyText.bin");
     }
 
+    #[test]
+    fn round_trip_unaligned_data() {
+        let bytes = load_test_file("ArchiveTest_UnalignedData.bin");
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(6, archive.size());
+        assert_eq!(Some("Hello".to_string()), archive.read_string(0).unwrap());
+        assert_eq!(0xBBAA, archive.read_u16(4).unwrap());
+        assert_eq!(Some(0), archive.find_label_address("Label"));
+        assert_eq!(bytes, archive.serialize().unwrap());
+    }
+
+    #[test]
+    fn serialize_preserving_order() {
+        let bytes = load_test_file("ArchiveTest_Mixed1.bin");
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(bytes, archive.serialize_preserving_order().unwrap());
+
+        // Swap the first two pointer table entries.
+        let data_size = Endian::Little.decode_u32(&bytes[4..8]).unwrap() as usize;
+        let table = data_size.next_multiple_of(4) + 0x20;
+        let mut unsorted = bytes.clone();
+        unsorted[table..table + 4].copy_from_slice(&bytes[table + 4..table + 8]);
+        unsorted[table + 4..table + 8].copy_from_slice(&bytes[table..table + 4]);
+        let archive = BinArchive::from_bytes(&unsorted, Endian::Little).unwrap();
+        assert_eq!(bytes, archive.serialize().unwrap());
+        assert_eq!(unsorted, archive.serialize_preserving_order().unwrap());
+    }
+
+    #[test]
+    fn from_bytes_huge_counts() {
+        let counts = [
+            (0x4000_0001u32, 0u32),
+            (0, 0x2000_0001),
+            (u32::MAX, u32::MAX),
+        ];
+        for (pointer_count, label_count) in &counts {
+            let mut bytes = vec![0; 0x40];
+            bytes[0..4].copy_from_slice(&0x40u32.to_le_bytes());
+            bytes[8..12].copy_from_slice(&pointer_count.to_le_bytes());
+            bytes[12..16].copy_from_slice(&label_count.to_le_bytes());
+            assert!(matches!(
+                BinArchive::from_bytes(&bytes, Endian::Little),
+                Err(ArchiveError::ArchiveTooSmall)
+            ));
+        }
+    }
+
+    #[test]
+    fn has_pointer_string_labels() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(12);
+        archive.write_pointer(0, Some(8)).unwrap();
+        archive.write_string(4, Some("Text")).unwrap();
+        archive.write_label(8, "Label").unwrap();
+        assert!(archive.has_pointer(0));
+        assert!(!archive.has_pointer(4));
+        assert!(archive.has_string(4));
+        assert!(!archive.has_string(0));
+        assert!(archive.has_labels(8));
+        assert!(!archive.has_labels(0));
+        assert!(!archive.has_pointer(12));
+        assert!(!archive.has_string(usize::MAX));
+    }
+
+    #[test]
+    fn index_only() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(16);
+        archive.write_label(0, "First").unwrap();
+        archive.write_u32(0, 1).unwrap();
+        archive.write_pointer(4, Some(8)).unwrap();
+        archive.write_label(8, "Second").unwrap();
+        archive.write_u32(12, 2).unwrap();
+        let bytes = archive.serialize().unwrap();
+
+        let index = BinArchive::index_only(&bytes, Endian::Little).unwrap();
+        assert_eq!(16, index.data_size);
+        assert_eq!(vec![4], index.pointers);
+        let labels = vec![(0, "First".to_string()), (8, "Second".to_string())];
+        assert_eq!(labels, index.labels);
+
+        let first = BinArchive::read_record(&bytes, &index, "First").unwrap();
+        assert_eq!(Some(&bytes[0x20..0x28]), first);
+        let second = BinArchive::read_record(&bytes, &index, "Second").unwrap();
+        assert_eq!(Some(&[0, 0, 0, 0, 2, 0, 0, 0][..]), second);
+        let third = BinArchive::read_record(&bytes, &index, "Third").unwrap();
+        assert!(third.is_none());
+    }
+
+    #[test]
+    fn read_record_label_past_data() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(8);
+        archive.write_label(4, "Record").unwrap();
+        let bytes = archive.serialize().unwrap();
+
+        let mut index = BinArchive::index_only(&bytes, Endian::Little).unwrap();
+        index.labels = vec![(12, "Record".to_string())];
+        assert!(matches!(
+            BinArchive::read_record(&bytes, &index, "Record"),
+            Err(ArchiveError::OutOfBoundsAddress(12, 8))
+        ));
+    }
+
+    #[test]
+    fn validate_and_report() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(16);
+        archive.write_label(12, "Section").unwrap();
+        archive.write_pointer(0, Some(12)).unwrap();
+        archive.write_pointer(4, Some(8)).unwrap();
+        archive.write_pointer(8, Some(6)).unwrap();
+        assert_eq!(
+            vec![
+                ArchiveWarning::OffByFour {
+                    address: 4,
+                    destination: 8,
+                    label_address: 12
+                },
+                ArchiveWarning::UnalignedDestination {
+                    address: 8,
+                    destination: 6
+                },
+            ],
+            archive.validate_and_report()
+        );
+    }
+
+    #[test]
+    fn validate_and_report_c_strings() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(8);
+        archive.write_c_string(0, "AB".to_string()).unwrap();
+        archive.write_c_string(4, "CDE".to_string()).unwrap();
+        let bytes = archive.serialize().unwrap();
+
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(Some("CDE".to_string()), archive.read_c_string(4).unwrap());
+        assert!(archive.validate_and_report().is_empty());
+    }
+
+    #[test]
+    fn unclaimed_cells() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(26);
+        archive.write_u32(0, 5).unwrap();
+        archive.write_pointer(4, Some(0)).unwrap();
+        archive.write_string(8, Some("Text")).unwrap();
+        archive.write_c_string(12, "CString".to_string()).unwrap();
+        archive.write_label(16, "Label").unwrap();
+        assert_eq!(vec![0, 20], archive.unclaimed_cells());
+    }
+
+    #[test]
+    fn build_label_index() {
+        let bytes = load_test_file("ArchiveTest_MultiLabel.bin");
+        let mut archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        archive.write_label(8, "Alpha").unwrap();
+        let index = archive.build_label_index();
+        assert_eq!(3, index.len());
+        assert_eq!(Some(&0), index.get("Zeta"));
+        assert_eq!(Some(&0), index.get("Alpha"));
+        assert_eq!(Some(&4), index.get("Mid"));
+        for (label, address) in &index {
+            if label != "Alpha" {
+                assert_eq!(Some(*address), archive.find_label_address(label));
+            }
+        }
+    }
+
+    #[test]
+    fn clear_region() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(16);
+        archive.write_u32(0, 0xFFFFFFFF).unwrap();
+        archive.write_pointer(4, Some(12)).unwrap();
+        archive.write_string(8, Some("Text")).unwrap();
+        archive.write_c_string(12, "CString".to_string()).unwrap();
+        archive.write_label(4, "Record").unwrap();
+        archive.write_label(12, "Kept").unwrap();
+
+        archive.clear_region(0, 12).unwrap();
+        assert_eq!(16, archive.size());
+        assert_eq!(vec![0; 12], archive.read_bytes(0, 12).unwrap());
+        assert_eq!(None, archive.read_pointer(4).unwrap());
+        assert_eq!(None, archive.read_string(8).unwrap());
+        assert_eq!(None, archive.find_label_address("Record"));
+        assert_eq!(Some(12), archive.find_label_address("Kept"));
+        let bytes = archive.serialize().unwrap();
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(
+            Some("CString".to_string()),
+            archive.read_c_string(12).unwrap()
+        );
+        assert!(BinArchive::new(Endian::Little).clear_region(0, 4).is_err());
+    }
+
+    #[test]
+    fn append_archive() {
+        let mut first = BinArchive::new(Endian::Little);
+        first.allocate_at_end(8);
+        first.write_label(0, "First").unwrap();
+        first.write_pointer(0, Some(4)).unwrap();
+
+        let mut second = BinArchive::new(Endian::Little);
+        second.allocate_at_end(12);
+        second.write_label(0, "Second").unwrap();
+        second.write_pointer(0, Some(8)).unwrap();
+        second.write_string(4, Some("Text")).unwrap();
+        second.write_c_string(8, "CString".to_string()).unwrap();
+
+        assert_eq!(8, first.append_archive(&second).unwrap());
+        assert_eq!(20, first.size());
+        assert_eq!(Some(4), first.read_pointer(0).unwrap());
+        assert_eq!(Some(16), first.read_pointer(8).unwrap());
+        assert_eq!(Some("Text".to_string()), first.read_string(12).unwrap());
+        assert_eq!(Some(8), first.find_label_address("Second"));
+        let bytes = first.serialize().unwrap();
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(
+            Some("CString".to_string()),
+            archive.read_c_string(16).unwrap()
+        );
+        assert!(first.append_archive(&BinArchive::new(Endian::Big)).is_err());
+    }
+
+    #[test]
+    fn append_archive_aligns_base() {
+        let mut first = BinArchive::new(Endian::Little);
+        first.allocate_at_end(6);
+        let mut second = BinArchive::new(Endian::Little);
+        second.allocate_at_end(8);
+        second.write_label(0, "Second").unwrap();
+        second.write_pointer(0, Some(4)).unwrap();
+
+        assert_eq!(8, first.append_archive(&second).unwrap());
+        assert_eq!(16, first.size());
+        assert_eq!(Some(12), first.read_pointer(8).unwrap());
+        assert_eq!(Some(8), first.find_label_address("Second"));
+
+        second.set_trailer(vec![1, 2, 3, 4]);
+        assert!(first.append_archive(&second).is_err());
+        assert_eq!(16, first.size());
+    }
+
+    #[test]
+    fn extract_sub_archive() {
+        let inner = load_test_file("ArchiveTest_Mixed1.bin");
+        let mut outer = BinArchive::new(Endian::Little);
+        outer.allocate_at_end(4 + inner.len());
+        outer.write_label(0, "Outer").unwrap();
+        outer.write_bytes(4, &inner).unwrap();
+
+        let archive = outer.extract_sub_archive(4, inner.len()).unwrap();
+        assert_eq!(inner, archive.serialize().unwrap());
+        assert!(outer.extract_sub_archive(4, inner.len() + 4).is_err());
+    }
+
+    #[test]
+    fn serialized_size() {
+        for file in &[
+            "ArchiveTest_Mixed1.bin",
+            "ArchiveTest_Mixed2.bin",
+            "ArchiveTest_OnlyText.bin",
+            "ArchiveTest_UnalignedData.bin",
+            "ArchiveTest_MultiLabel.bin",
+        ] {
+            let bytes = load_test_file(file);
+            let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+            assert_eq!(bytes.len(), archive.serialized_size().unwrap());
+        }
+
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(14);
+        archive.write_label(0, "Shared").unwrap();
+        archive.write_string(0, Some("Shared")).unwrap();
+        archive.write_c_string(4, "Shared".to_string()).unwrap();
+        archive.write_c_string(8, "Unique".to_string()).unwrap();
+        archive.set_trailer(vec![1, 2, 3]);
+        assert_eq!(
+            archive.serialize().unwrap().len(),
+            archive.serialized_size().unwrap()
+        );
+    }
+
+    #[test]
+    fn string_fits() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(8);
+        archive.write_string(0, Some("テスト")).unwrap();
+        assert!(archive.string_fits(0, "abcdef").unwrap());
+        assert!(archive.string_fits(0, "テキ").unwrap());
+        assert!(!archive.string_fits(0, "abcdefg").unwrap());
+        assert!(!archive.string_fits(4, "a").unwrap());
+        assert!(archive.string_fits(8, "a").is_err());
+    }
+
+    #[test]
+    fn round_trip_multi_label() {
+        test_archive_for_success("ArchiveTest_MultiLabel.bin");
+        let bytes = load_test_file("ArchiveTest_MultiLabel.bin");
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(
+            Some(vec!["Zeta".to_string(), "Alpha".to_string()]),
+            archive.read_labels(0).unwrap()
+        );
+    }
+
+    #[test]
+    fn round_trip_multi_label_big_endian() {
+        // Big endian tables are sorted by label, so a bucket's labels need not be adjacent.
+        let mut archive = BinArchive::new(Endian::Big);
+        archive.allocate_at_end(8);
+        archive.write_label(0, "Zeta").unwrap();
+        archive.write_label(4, "Mid").unwrap();
+        archive.write_label(0, "Alpha").unwrap();
+        let bytes = archive.serialize().unwrap();
+        let archive = BinArchive::from_bytes(&bytes, Endian::Big).unwrap();
+        assert_eq!(
+            Some(vec!["Alpha".to_string(), "Zeta".to_string()]),
+            archive.read_labels(0).unwrap()
+        );
+        assert_eq!(bytes, archive.serialize().unwrap());
+    }
+
     #[test]
     fn round_trip_mixed1() {
         test_archive_for_success("ArchiveTest_Mixed1.bin");
@@ -112,6 +112,26 @@ impl<'a> BinArchiveReader<'a> {
         self.position += 4;
         Ok(value)
     }
+
+    // Reads a u32 count followed by that many pointers, leaving the reader after the last one.
+    // The count doesn't include a terminator. Null entries are an error since they'd shift indices.
+    pub fn read_pointer_table(&mut self) -> Result<Vec<usize>> {
+        let count = self.read_u32()? as usize;
+        let mut destinations = Vec::with_capacity(count.min(self.archive.size() / 4));
+        for _ in 0..count {
+            let address = self.position;
+            match self.read_pointer()? {
+                Some(destination) => destinations.push(destination),
+                None => {
+                    return Err(ArchiveError::OtherError(format!(
+                        "Null pointer at 0x{:X} in pointer table.",
+                        address
+                    )))
+                }
+            }
+        }
+        Ok(destinations)
+    }
 }
 
 impl<'a> BinArchiveWriter<'a> {
@@ -216,4 +236,43 @@ impl<'a> BinArchiveWriter<'a> {
         self.position += 4;
         Ok(())
     }
+
+    // Labels the current position, then writes and advances like the unlabeled versions.
+    pub fn write_labeled_u32(&mut self, label: &str, value: u32) -> Result<()> {
+        self.write_label(label)?;
+        self.write_u32(value)
+    }
+
+    pub fn write_labeled_string(&mut self, label: &str, value: Option<&str>) -> Result<()> {
+        self.write_label(label)?;
+        self.write_string(value)
+    }
+
+    pub fn write_labeled_pointer(&mut self, label: &str, value: Option<usize>) -> Result<()> {
+        self.write_label(label)?;
+        self.write_pointer(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Endian;
+
+    #[test]
+    fn read_pointer_table() {
+        let mut archive = BinArchive::new(Endian::Little);
+        archive.allocate_at_end(20);
+        archive.write_u32(0, 2).unwrap();
+        archive.write_pointer(4, Some(12)).unwrap();
+        archive.write_pointer(8, Some(16)).unwrap();
+
+        let mut reader = BinArchiveReader::new(&archive, 0);
+        assert_eq!(vec![12, 16], reader.read_pointer_table().unwrap());
+        assert_eq!(12, reader.tell());
+
+        archive.write_u32(0, 3).unwrap();
+        let mut reader = BinArchiveReader::new(&archive, 0);
+        assert!(reader.read_pointer_table().is_err());
+    }
 }
@@ -1,13 +1,18 @@
-use crate::texture::Texture;
-use crate::{texture_decoder, TextureParseError};
+use crate::texture::{Texture, TextureSourceFormat};
+use crate::texture_decoder::{self, HardwareTextureFormat};
+use crate::texture_utils::TextureLengthMismatch;
+use crate::{texture_utils, TextureContainer, TextureParseError, TextureWriteOptions};
 use byteorder::{LittleEndian, ReadBytesExt};
-use encoding_rs::UTF_8;
+use encoding_rs::{SHIFT_JIS, UTF_8};
 use std::io::{BufRead, Cursor, Read, Seek, SeekFrom};
 
 // Source: https://www.3dbrew.org/wiki/CGFX
 
 type Result<T> = std::result::Result<T, TextureParseError>;
 
+// CGFX is a 3DS format, so everything is little endian.
+type FileEndian = LittleEndian;
+
 #[allow(dead_code)]
 struct Header {
     magic_id: u32,
@@ -20,15 +25,15 @@ struct Header {
 
 impl Header {
     fn new(reader: &mut Cursor<&[u8]>) -> Result<Self> {
-        let magic_id = reader.read_u32::<LittleEndian>()?;
+        let magic_id = reader.read_u32::<FileEndian>()?;
         if magic_id != 0x58464743 {
             return Err(TextureParseError::BadMagicNumber);
         }
-        let byte_order_mark = reader.read_u16::<LittleEndian>()?; // Redudant
-        let struct_size = reader.read_u16::<LittleEndian>()?;
-        let revision = reader.read_u32::<LittleEndian>()?;
-        let file_size = reader.read_u32::<LittleEndian>()?;
-        let entry_count = reader.read_u32::<LittleEndian>()?;
+        let byte_order_mark = reader.read_u16::<FileEndian>()?; // Redudant
+        let struct_size = reader.read_u16::<FileEndian>()?;
+        let revision = reader.read_u32::<FileEndian>()?;
+        let file_size = reader.read_u32::<FileEndian>()?;
+        let entry_count = reader.read_u32::<FileEndian>()?;
 
         Ok(Header {
             magic_id,
@@ -50,12 +55,12 @@ struct DATA {
 
 impl DATA {
     fn new(reader: &mut Cursor<&[u8]>) -> Result<Self> {
-        let magic_id = reader.read_u32::<LittleEndian>()?;
-        let struct_size = reader.read_u32::<LittleEndian>()?;
+        let magic_id = reader.read_u32::<FileEndian>()?;
+        let struct_size = reader.read_u32::<FileEndian>()?;
         let mut entry: Vec<DATAEntry> = Vec::new();
         for _i in 0..16 {
-            let entry_count = reader.read_u32::<LittleEndian>()?;
-            let offset = reader.position() as u32 + reader.read_u32::<LittleEndian>()?;
+            let entry_count = reader.read_u32::<FileEndian>()?;
+            let offset = reader.position() as u32 + reader.read_u32::<FileEndian>()?;
             entry.push(DATAEntry {
                 entry_count,
                 offset,
@@ -85,15 +90,15 @@ struct DICT {
 
 impl DICT {
     fn new(reader: &mut Cursor<&[u8]>) -> Result<Self> {
-        let magic_id = reader.read_u32::<LittleEndian>()?;
-        let struct_size = reader.read_u32::<LittleEndian>()?;
-        let entry_count = reader.read_u32::<LittleEndian>()?;
+        let magic_id = reader.read_u32::<FileEndian>()?;
+        let struct_size = reader.read_u32::<FileEndian>()?;
+        let entry_count = reader.read_u32::<FileEndian>()?;
         reader.seek(SeekFrom::Current(0x10))?;
         let mut entry: Vec<DICTEntry> = Vec::new();
         for _i in 0..entry_count {
             reader.seek(SeekFrom::Current(0x8))?;
-            let filename_offset = reader.position() as u32 + reader.read_u32::<LittleEndian>()?;
-            let object_offset = reader.position() as u32 + reader.read_u32::<LittleEndian>()?;
+            let filename_offset = reader.position() as u32 + reader.read_u32::<FileEndian>()?;
+            let object_offset = reader.position() as u32 + reader.read_u32::<FileEndian>()?;
             entry.push(DICTEntry {
                 filename_offset,
                 object_offset,
@@ -133,20 +138,20 @@ impl TXOB {
         let mut txob: Vec<TXOB> = Vec::new();
         for i in 0..dict.entry_count as usize {
             reader.seek(SeekFrom::Start(dict.entry[i].object_offset as u64))?;
-            let flags = reader.read_u32::<LittleEndian>()?;
-            let magic_id = reader.read_u32::<LittleEndian>()?;
+            let flags = reader.read_u32::<FileEndian>()?;
+            let magic_id = reader.read_u32::<FileEndian>()?;
             reader.seek(SeekFrom::Current(0x4))?;
-            let filename_offset = reader.position() as u32 + reader.read_u32::<LittleEndian>()?;
+            let filename_offset = reader.position() as u32 + reader.read_u32::<FileEndian>()?;
             reader.seek(SeekFrom::Current(0x8))?;
-            let height = reader.read_u32::<LittleEndian>()? as usize;
-            let width = reader.read_u32::<LittleEndian>()? as usize;
+            let height = reader.read_u32::<FileEndian>()? as usize;
+            let width = reader.read_u32::<FileEndian>()? as usize;
             reader.seek(SeekFrom::Current(0x8))?;
-            let mipmap_levels = reader.read_u32::<LittleEndian>()?;
+            let mipmap_levels = reader.read_u32::<FileEndian>()?;
             reader.seek(SeekFrom::Current(0x8))?;
-            let pixel_format = reader.read_u32::<LittleEndian>()?;
+            let pixel_format = reader.read_u32::<FileEndian>()?;
             reader.seek(SeekFrom::Current(0xC))?;
-            let size = reader.read_u32::<LittleEndian>()? as usize;
-            let texture_offset = reader.position() as u32 + reader.read_u32::<LittleEndian>()?;
+            let size = reader.read_u32::<FileEndian>()? as usize;
+            let texture_offset = reader.position() as u32 + reader.read_u32::<FileEndian>()?;
             txob.push(TXOB {
                 flags,
                 magic_id,
@@ -161,50 +166,286 @@ impl TXOB {
         }
         Ok(txob)
     }
+
+    // Checked per texture so one unknown format doesn't stop the rest from being read.
+    fn hardware_format(&self) -> Result<HardwareTextureFormat> {
+        HardwareTextureFormat::from_u32(self.pixel_format)
+            .ok_or(TextureParseError::UnknownPixelFormat(self.pixel_format))
+    }
 }
 
-fn parse_textures(reader: &mut Cursor<&[u8]>, txob: &Vec<TXOB>) -> Result<Vec<Texture>> {
-    let mut textures: Vec<Texture> = Vec::new();
-    // Read pixel data
-    for txob_file in txob {
-        let mut pixel_data: Vec<u8> = vec![0; txob_file.size];
-        reader.seek(SeekFrom::Start(txob_file.texture_offset as u64))?;
-        reader.read_exact(&mut pixel_data)?;
+// Names are usually UTF-8, but some files use Shift-JIS like CTPK does.
+fn decode_filename(buffer: &[u8]) -> Result<String> {
+    let (result, _, errors) = UTF_8.decode(buffer);
+    if !errors {
+        return Ok(result.into());
+    }
+    let (result, _, errors) = SHIFT_JIS.decode(buffer);
+    if errors {
+        return Err(TextureParseError::BadText);
+    }
+    Ok(result.into())
+}
 
-        // Read filename
-        reader.seek(SeekFrom::Start(txob_file.filename_offset as u64))?;
-        let mut filename_buffer: Vec<u8> = Vec::new();
-        reader.read_until(0x0, &mut filename_buffer)?;
-        filename_buffer.pop(); // Get rid of the null terminator.
-        let (result, _, errors) = UTF_8.decode(filename_buffer.as_slice());
-        if errors {
-            return Err(TextureParseError::BadText);
-        }
-        let filename: String = result.into();
-        let width = txob_file.width;
-        let height = txob_file.height;
-        let pixel_format = txob_file.pixel_format;
-        let pixel_data =
-            texture_decoder::decode_pixel_data(&pixel_data, width, height, pixel_format)?;
-        textures.push(Texture {
-            filename,
-            width,
-            height,
-            pixel_data,
-        });
-    }
-    Ok(textures)
+fn read_filename(reader: &mut Cursor<&[u8]>, filename_offset: u32) -> Result<String> {
+    reader.seek(SeekFrom::Start(filename_offset as u64))?;
+    let mut filename_buffer: Vec<u8> = Vec::new();
+    reader.read_until(0x0, &mut filename_buffer)?;
+    filename_buffer.pop(); // Get rid of the null terminator.
+    decode_filename(&filename_buffer)
+}
+
+fn parse_texture(reader: &mut Cursor<&[u8]>, txob_file: &TXOB) -> Result<Texture> {
+    let mut raw: Vec<u8> = vec![0; txob_file.size];
+    reader.seek(SeekFrom::Start(txob_file.texture_offset as u64))?;
+    reader.read_exact(&mut raw)?;
+
+    let filename = read_filename(reader, txob_file.filename_offset)?;
+    let width = txob_file.width;
+    let height = txob_file.height;
+    let pixel_format = txob_file.hardware_format()?;
+    let pixel_data = texture_decoder::decode_pixel_data(&raw, width, height, pixel_format)?;
+    Ok(Texture {
+        filename,
+        width,
+        height,
+        pixel_data,
+        raw,
+        source_format: Some(TextureSourceFormat::Pica(pixel_format)),
+    })
+}
+
+fn read_txob(reader: &mut Cursor<&[u8]>) -> Result<Vec<TXOB>> {
+    let _header = Header::new(reader)?;
+    let data = DATA::new(reader)?;
+
+    // Going to skip a recursive loop of DICT and just access the texture entry;
+    // Model-only containers have no texture section, so there's nothing to read.
+    let texture_entry = match data.entry.get(1) {
+        Some(entry) if entry.entry_count > 0 => entry,
+        _ => return Ok(Vec::new()),
+    };
+    reader.seek(SeekFrom::Start(texture_entry.offset as u64))?;
+    let dict = DICT::new(reader)?;
+    TXOB::new(reader, dict)
+}
+
+pub fn texture_count(file: &[u8]) -> Result<usize> {
+    Ok(read_header(file)?.texture_count)
+}
+
+// Header fields that are useful without decoding anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicHeader {
+    pub revision: u32,
+    pub file_size: u32,
+    pub section_count: u32,
+    pub texture_count: usize,
+}
+
+pub fn read_header(file: &[u8]) -> Result<PublicHeader> {
+    let mut reader = Cursor::new(file);
+    let header = Header::new(&mut reader)?;
+    let data = DATA::new(&mut reader)?;
+    let texture_count = data.entry.get(1).map_or(0, |entry| entry.entry_count);
+    Ok(PublicHeader {
+        revision: header.revision,
+        file_size: header.file_size,
+        section_count: header.entry_count,
+        texture_count: texture_count as usize,
+    })
 }
 
 pub fn read(file: &[u8]) -> Result<Vec<Texture>> {
     let mut reader = Cursor::new(file);
+    let txob = read_txob(&mut reader)?;
+    txob.iter()
+        .map(|txob_file| parse_texture(&mut reader, txob_file))
+        .collect()
+}
 
-    let _header = Header::new(&mut reader)?;
-    let data = DATA::new(&mut reader)?;
+pub fn read_lenient(file: &[u8]) -> (Vec<Texture>, Vec<TextureParseError>) {
+    let mut reader = Cursor::new(file);
+    match read_txob(&mut reader) {
+        Ok(txob) => texture_utils::collect_lenient(
+            txob.iter()
+                .map(|txob_file| parse_texture(&mut reader, txob_file)),
+        ),
+        Err(err) => (Vec::new(), vec![err]),
+    }
+}
 
-    // Going to skip a recursive loop of DICT and just access the texture entry;
-    reader.seek(SeekFrom::Start(data.entry[1].offset as u64))?;
-    let dict = DICT::new(&mut reader)?;
-    let txob = TXOB::new(&mut reader, dict)?;
-    parse_textures(&mut reader, &txob)
+// Compares each texture's declared length against its dimensions and format.
+pub fn check_lengths(file: &[u8]) -> Result<Vec<TextureLengthMismatch>> {
+    let mut reader = Cursor::new(file);
+    let txob = read_txob(&mut reader)?;
+    let mut mismatches = Vec::new();
+    for txob_file in &txob {
+        let filename = read_filename(&mut reader, txob_file.filename_offset)?;
+        mismatches.extend(texture_utils::check_texture_length(
+            &filename,
+            txob_file.width,
+            txob_file.height,
+            txob_file.hardware_format()?,
+            txob_file.mipmap_levels as usize,
+            txob_file.size,
+        ));
+    }
+    Ok(mismatches)
+}
+
+pub fn read_mip(file: &[u8], texture_index: usize, level: usize) -> Result<Texture> {
+    let mut reader = Cursor::new(file);
+    let txob = read_txob(&mut reader)?;
+    let txob_file = txob
+        .get(texture_index)
+        .ok_or(TextureParseError::TextureIndexOutOfRange(texture_index))?;
+    if level >= txob_file.mipmap_levels.max(1) as usize {
+        return Err(TextureParseError::MipLevelOutOfRange(level));
+    }
+
+    let pixel_format = txob_file.hardware_format()?;
+    let width = txob_file.width >> level;
+    let height = txob_file.height >> level;
+    let offset =
+        texture_decoder::get_mip_offset(txob_file.width, txob_file.height, pixel_format, level);
+    let mut raw: Vec<u8> = vec![0; texture_decoder::get_level_size(width, height, pixel_format)];
+    reader.seek(SeekFrom::Start(
+        txob_file.texture_offset as u64 + offset as u64,
+    ))?;
+    reader.read_exact(&mut raw)?;
+
+    let filename = read_filename(&mut reader, txob_file.filename_offset)?;
+    let pixel_data = texture_decoder::decode_pixel_data(&raw, width, height, pixel_format)?;
+    Ok(Texture {
+        filename,
+        width,
+        height,
+        pixel_data,
+        raw,
+        source_format: Some(TextureSourceFormat::Pica(pixel_format)),
+    })
+}
+
+pub struct Cgfx;
+
+impl TextureContainer for Cgfx {
+    fn read(bytes: &[u8]) -> Result<Vec<Texture>> {
+        read(bytes)
+    }
+
+    fn write(_textures: &[Texture], _options: &TextureWriteOptions) -> Result<Vec<u8>> {
+        Err(TextureParseError::WriteNotSupported("CGFX".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::load_test_file;
+
+    #[test]
+    fn read_without_textures() {
+        let mut file: Vec<u8> = Vec::new();
+        file.extend(&0x58464743u32.to_le_bytes());
+        file.extend(&0xFEFFu16.to_le_bytes());
+        file.extend(&0x14u16.to_le_bytes());
+        file.extend(&0u32.to_le_bytes());
+        file.extend(&0u32.to_le_bytes());
+        file.extend(&1u32.to_le_bytes());
+        file.extend(&0x41544144u32.to_le_bytes());
+        file.resize(file.len() + 4 + 16 * 8, 0);
+
+        assert_eq!(0, texture_count(&file).unwrap());
+        assert!(read(&file).unwrap().is_empty());
+        let (textures, errors) = read_lenient(&file);
+        assert!(textures.is_empty() && errors.is_empty());
+        assert!(matches!(
+            super::read_mip(&file, 0, 0),
+            Err(TextureParseError::TextureIndexOutOfRange(0))
+        ));
+        let (_, errors) = read_lenient(&file[..0x10]);
+        assert_eq!(1, errors.len());
+    }
+
+    #[test]
+    fn height_before_width() {
+        let file = load_test_file("Cgfx_Test.bin");
+        let textures = read(&file).unwrap();
+        assert_eq!(1, textures.len());
+        assert_eq!("tex", textures[0].filename);
+        assert_eq!(16, textures[0].width);
+        assert_eq!(8, textures[0].height);
+        assert_eq!(16 * 8 * 4, textures[0].pixel_data.len());
+    }
+
+    #[test]
+    fn read_lenient_unknown_pixel_format() {
+        let mut file = load_test_file("Cgfx_TwoTextures.bin");
+        assert_eq!(2, read(&file).unwrap().len());
+
+        // Pixel format of the second TXOB.
+        file[0x194..0x198].copy_from_slice(&0x2Au32.to_le_bytes());
+        assert!(read(&file).is_err());
+        let (textures, errors) = read_lenient(&file);
+        assert_eq!(1, textures.len());
+        assert_eq!("a", textures[0].filename);
+        assert!(matches!(
+            errors[..],
+            [TextureParseError::UnknownPixelFormat(0x2A)]
+        ));
+    }
+
+    #[test]
+    fn read_mip() {
+        let file = load_test_file("Cgfx_TwoTextures.bin");
+        let texture = super::read_mip(&file, 1, 0).unwrap();
+        assert_eq!("b", texture.filename);
+        assert_eq!(vec![0x80; 8 * 8 * 4], texture.pixel_data);
+        assert!(matches!(
+            super::read_mip(&file, 2, 0),
+            Err(TextureParseError::TextureIndexOutOfRange(2))
+        ));
+        assert!(matches!(
+            super::read_mip(&file, 0, 1),
+            Err(TextureParseError::MipLevelOutOfRange(1))
+        ));
+    }
+
+    #[test]
+    fn read_header() {
+        let mut file = load_test_file("Cgfx_Test.bin");
+        file[0x8..0xC].copy_from_slice(&0x05000000u32.to_le_bytes());
+        let file_size = file.len() as u32;
+        file[0xC..0x10].copy_from_slice(&file_size.to_le_bytes());
+        let header = super::read_header(&file).unwrap();
+        assert_eq!(0x05000000, header.revision);
+        assert_eq!(file_size, header.file_size);
+        assert_eq!(1, header.section_count);
+        assert_eq!(1, header.texture_count);
+        assert!(super::read_header(&[0; 0x40]).is_err());
+    }
+
+    #[test]
+    fn check_lengths() {
+        let mut file = load_test_file("Cgfx_Test.bin");
+        assert!(super::check_lengths(&file).unwrap().is_empty());
+        file[0x10C..0x110].copy_from_slice(&(16u32 * 8 * 2).to_le_bytes());
+        let mismatches = super::check_lengths(&file).unwrap();
+        assert_eq!(
+            vec![TextureLengthMismatch {
+                filename: "tex".to_string(),
+                expected: 16 * 8 * 4,
+                declared: 16 * 8 * 2,
+            }],
+            mismatches
+        );
+    }
+
+    #[test]
+    fn decode_filename_falls_back_to_shift_jis() {
+        assert_eq!("tex_a", decode_filename(b"tex_a").unwrap());
+        let (bytes, _, _) = SHIFT_JIS.encode("顔_テクスチャ");
+        assert_eq!("顔_テクスチャ", decode_filename(&bytes).unwrap());
+    }
 }
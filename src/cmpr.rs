@@ -0,0 +1,133 @@
+use crate::padding::align;
+use crate::{Endian, TextureDecodeError};
+
+type Result<T> = std::result::Result<T, TextureDecodeError>;
+
+const TILE_SIZE: usize = 8;
+const SUB_BLOCK_SIZE: usize = 4;
+const SUB_BLOCK_BYTES: usize = 8;
+
+fn rgb565_to_rgba(value: u16) -> [u8; 4] {
+    let r = ((value >> 11) & 0x1F) as u8;
+    let g = ((value >> 5) & 0x3F) as u8;
+    let b = (value & 0x1F) as u8;
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+        0xFF,
+    ]
+}
+
+fn interpolate(a: &[u8; 4], b: &[u8; 4], a_weight: u32, b_weight: u32) -> [u8; 4] {
+    let total = a_weight + b_weight;
+    let mix = |i: usize| ((a[i] as u32 * a_weight + b[i] as u32 * b_weight) / total) as u8;
+    [mix(0), mix(1), mix(2), 0xFF]
+}
+
+fn decode_palette(block: &[u8]) -> Result<[[u8; 4]; 4]> {
+    let raw0 = Endian::Big.decode_u16(&block[0..2])?;
+    let raw1 = Endian::Big.decode_u16(&block[2..4])?;
+    let color0 = rgb565_to_rgba(raw0);
+    let color1 = rgb565_to_rgba(raw1);
+    if raw0 > raw1 {
+        Ok([
+            color0,
+            color1,
+            interpolate(&color0, &color1, 2, 1),
+            interpolate(&color0, &color1, 1, 2),
+        ])
+    } else {
+        // Same as DXT1: the fourth entry becomes fully transparent.
+        Ok([
+            color0,
+            color1,
+            interpolate(&color0, &color1, 1, 1),
+            [0, 0, 0, 0],
+        ])
+    }
+}
+
+// CMPR stores 8x8 tiles, each made up of four 4x4 DXT1 blocks in row-major order.
+pub fn decode(data: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    let aligned_width = align(width, TILE_SIZE);
+    let aligned_height = align(height, TILE_SIZE);
+    let num_blocks = (aligned_width / SUB_BLOCK_SIZE) * (aligned_height / SUB_BLOCK_SIZE);
+    if data.len() < num_blocks * SUB_BLOCK_BYTES {
+        return Err(TextureDecodeError::BadDimensions {
+            width,
+            height,
+            expected_len: num_blocks * SUB_BLOCK_BYTES,
+            actual_len: data.len(),
+        });
+    }
+
+    let mut bmp: Vec<u8> = vec![0; 4 * width * height];
+    let mut pos = 0;
+    for tile_y in (0..aligned_height).step_by(TILE_SIZE) {
+        for tile_x in (0..aligned_width).step_by(TILE_SIZE) {
+            for sub_block in 0..4 {
+                let block = &data[pos..pos + SUB_BLOCK_BYTES];
+                pos += SUB_BLOCK_BYTES;
+                let palette = decode_palette(block)?;
+                let block_x = tile_x + (sub_block % 2) * SUB_BLOCK_SIZE;
+                let block_y = tile_y + (sub_block / 2) * SUB_BLOCK_SIZE;
+                for row in 0..SUB_BLOCK_SIZE {
+                    let indices = block[4 + row];
+                    for column in 0..SUB_BLOCK_SIZE {
+                        let x = block_x + column;
+                        let y = block_y + row;
+                        if x >= width || y >= height {
+                            continue;
+                        }
+                        let index = (indices >> (6 - column * 2)) & 0b11;
+                        let pixel_pos = (y * width + x) * 4;
+                        bmp[pixel_pos..pixel_pos + 4].copy_from_slice(&palette[index as usize]);
+                    }
+                }
+            }
+        }
+    }
+    Ok(bmp)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_single_tile() {
+        // Every sub-block is pure red (color0) in opaque mode, except the last
+        // sub-block which selects the transparent entry in 3-color mode.
+        let opaque_red = [0xF8, 0x00, 0x00, 0x00, 0, 0, 0, 0];
+        let transparent = [0x00, 0x00, 0xF8, 0x00, 0xFF, 0xFF, 0xFF, 0xFF];
+        let mut data = Vec::new();
+        for _ in 0..3 {
+            data.extend_from_slice(&opaque_red);
+        }
+        data.extend_from_slice(&transparent);
+
+        let bmp = decode(&data, 8, 8).unwrap();
+        assert_eq!(256, bmp.len());
+        assert_eq!(&[0xFF, 0, 0, 0xFF], &bmp[0..4]);
+        assert_eq!(&[0xFF, 0, 0, 0xFF], &bmp[(4 * 8) * 4..(4 * 8 + 1) * 4]);
+        assert_eq!(&[0, 0, 0, 0], &bmp[(7 * 8 + 7) * 4..]);
+    }
+
+    #[test]
+    fn decode_too_small() {
+        match decode(&[0; 8], 8, 8) {
+            Err(TextureDecodeError::BadDimensions {
+                width,
+                height,
+                expected_len,
+                actual_len,
+            }) => {
+                assert_eq!((8, 8), (width, height));
+                assert_eq!(32, expected_len);
+                assert_eq!(8, actual_len);
+            }
+            _ => panic!("expected BadDimensions"),
+        }
+    }
+}
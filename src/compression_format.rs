@@ -3,15 +3,25 @@ use crate::{LZ10CompressionFormat, LZ13CompressionFormat};
 
 type Result<T> = std::result::Result<T, CompressionError>;
 
+#[derive(Clone)]
+pub struct NoOpCompressionFormat;
+
 #[derive(Clone)]
 pub enum CompressionFormat {
+    NoOp(NoOpCompressionFormat),
     LZ10(LZ10CompressionFormat),
     LZ13(LZ13CompressionFormat),
 }
 
 impl CompressionFormat {
+    // Every format that can both compress and decompress. NoOp isn't listed.
+    pub fn supported() -> Vec<&'static str> {
+        vec!["LZ10", "LZ13"]
+    }
+
     pub fn is_compressed_filename(&self, filename: &str) -> bool {
         match self {
+            CompressionFormat::NoOp(_) => false,
             CompressionFormat::LZ10(c) => c.is_compressed_filename(filename),
             CompressionFormat::LZ13(c) => c.is_compressed_filename(filename),
         }
@@ -19,6 +29,7 @@ impl CompressionFormat {
 
     pub fn compress(&self, bytes: &[u8]) -> Result<Vec<u8>> {
         match self {
+            CompressionFormat::NoOp(_) => Ok(bytes.to_vec()),
             CompressionFormat::LZ10(c) => c.compress(bytes),
             CompressionFormat::LZ13(c) => c.compress(bytes),
         }
@@ -26,8 +37,40 @@ impl CompressionFormat {
 
     pub fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>> {
         match self {
+            CompressionFormat::NoOp(_) => Ok(bytes.to_vec()),
             CompressionFormat::LZ10(c) => c.decompress(bytes),
             CompressionFormat::LZ13(c) => c.decompress(bytes),
         }
     }
+
+    // Input that fails to decompress is reported as an error rather than Ok(false).
+    pub fn verify(&self, compressed: &[u8], expected_len: usize) -> Result<bool> {
+        Ok(self.decompress(compressed)?.len() == expected_len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::utils::load_test_file;
+
+    #[test]
+    fn supported() {
+        assert_eq!(vec!["LZ10", "LZ13"], CompressionFormat::supported());
+    }
+
+    #[test]
+    fn verify() {
+        let compressed = load_test_file("LZ13Test.bin.lz");
+        let expected_len = load_test_file("LZ13Test.bin").len();
+        let format = CompressionFormat::LZ13(LZ13CompressionFormat {});
+        assert!(format.verify(&compressed, expected_len).unwrap());
+        assert!(!format.verify(&compressed, expected_len + 1).unwrap());
+
+        let truncated = &compressed[..compressed.len() / 2];
+        assert!(matches!(
+            format.verify(truncated, expected_len),
+            Err(CompressionError::InvalidInput(_))
+        ));
+    }
 }
@@ -1,12 +1,20 @@
-use crate::texture::Texture;
-use crate::{texture_decoder, TextureParseError};
-use byteorder::{LittleEndian, ReadBytesExt};
+use crate::padding::{align, pad_to};
+use crate::texture::{Texture, TextureSourceFormat};
+use crate::texture_decoder::{self, HardwareTextureFormat};
+use crate::texture_utils::TextureLengthMismatch;
+use crate::{
+    texture_encoder, texture_utils, TextureContainer, TextureParseError, TextureWriteOptions,
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use encoding_rs::SHIFT_JIS;
 use std::io::prelude::BufRead;
 use std::io::{Cursor, Read, Seek, SeekFrom};
 
 type Result<T> = std::result::Result<T, TextureParseError>;
 
+// CTPK is a 3DS format, so everything is little endian.
+type FileEndian = LittleEndian;
+
 #[allow(dead_code)]
 pub struct Header {
     pub magic_id: u32,
@@ -20,13 +28,13 @@ pub struct Header {
 
 impl Header {
     pub fn new(reader: &mut Cursor<&[u8]>) -> Result<Self> {
-        let magic_id = reader.read_u32::<LittleEndian>()?;
-        let version = reader.read_u16::<LittleEndian>()?;
-        let texture_count = reader.read_u16::<LittleEndian>()?;
-        let texture_ptr = reader.read_u32::<LittleEndian>()?;
-        let texture_length = reader.read_u32::<LittleEndian>()?;
-        let hash_ptr = reader.read_u32::<LittleEndian>()?;
-        let texture_short_info_ptr = reader.read_u32::<LittleEndian>()?;
+        let magic_id = reader.read_u32::<FileEndian>()?;
+        let version = reader.read_u16::<FileEndian>()?;
+        let texture_count = reader.read_u16::<FileEndian>()?;
+        let texture_ptr = reader.read_u32::<FileEndian>()?;
+        let texture_length = reader.read_u32::<FileEndian>()?;
+        let hash_ptr = reader.read_u32::<FileEndian>()?;
+        let texture_short_info_ptr = reader.read_u32::<FileEndian>()?;
         reader.seek(SeekFrom::Current(0x8))?; // Skip padding
         Ok(Header {
             magic_id,
@@ -56,17 +64,17 @@ pub struct TextureInfo {
 
 impl TextureInfo {
     fn new(reader: &mut Cursor<&[u8]>) -> Result<Self> {
-        let filename_ptr = reader.read_u32::<LittleEndian>()?;
-        let texture_length = reader.read_u32::<LittleEndian>()?;
-        let texture_ptr = reader.read_u32::<LittleEndian>()?;
-        let pixel_format = reader.read_u32::<LittleEndian>()?;
-        let width = reader.read_u16::<LittleEndian>()? as usize;
-        let height = reader.read_u16::<LittleEndian>()? as usize;
+        let filename_ptr = reader.read_u32::<FileEndian>()?;
+        let texture_length = reader.read_u32::<FileEndian>()?;
+        let texture_ptr = reader.read_u32::<FileEndian>()?;
+        let pixel_format = reader.read_u32::<FileEndian>()?;
+        let width = reader.read_u16::<FileEndian>()? as usize;
+        let height = reader.read_u16::<FileEndian>()? as usize;
         let mipmap_level = reader.read_u8()?;
         let texture_type = reader.read_u8()?;
-        let cube_dir = reader.read_u16::<LittleEndian>()?;
-        let bitmap_size_ptr = reader.read_u32::<LittleEndian>()?;
-        let file_time = reader.read_u32::<LittleEndian>()?;
+        let cube_dir = reader.read_u16::<FileEndian>()?;
+        let bitmap_size_ptr = reader.read_u32::<FileEndian>()?;
+        let file_time = reader.read_u32::<FileEndian>()?;
         Ok(TextureInfo {
             filename_ptr,
             texture_length,
@@ -81,56 +89,507 @@ impl TextureInfo {
             file_time,
         })
     }
+
+    // Checked per texture so one unknown format doesn't stop the rest from being read.
+    pub fn hardware_format(&self) -> Result<HardwareTextureFormat> {
+        HardwareTextureFormat::from_u32(self.pixel_format)
+            .ok_or(TextureParseError::UnknownPixelFormat(self.pixel_format))
+    }
 }
 
-pub fn read(file: &[u8]) -> Result<Vec<Texture>> {
-    let mut reader = Cursor::new(file);
+pub fn texture_count(file: &[u8]) -> Result<usize> {
+    Ok(read_header(file)?.texture_count)
+}
 
+// Header fields that are useful without decoding anything.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicHeader {
+    pub version: u16,
+    pub texture_count: usize,
+    pub texture_data_offset: u32,
+    pub texture_data_length: u32,
+}
+
+pub fn read_header(file: &[u8]) -> Result<PublicHeader> {
+    let mut reader = Cursor::new(file);
     let header = Header::new(&mut reader)?;
+    if header.magic_id != CTPK_MAGIC {
+        return Err(TextureParseError::BadMagicNumber);
+    }
+    Ok(PublicHeader {
+        version: header.version,
+        texture_count: header.texture_count as usize,
+        texture_data_offset: header.texture_ptr,
+        texture_data_length: header.texture_length,
+    })
+}
+
+fn read_filename(reader: &mut Cursor<&[u8]>, filename_ptr: u32) -> Result<String> {
+    reader.seek(SeekFrom::Start(filename_ptr as u64))?;
+    let mut filename_buffer: Vec<u8> = Vec::new();
+    reader.read_until(0x0, &mut filename_buffer)?;
+    filename_buffer.pop(); // Get rid of the null terminator.
+    let (result, _, errors) = SHIFT_JIS.decode(filename_buffer.as_slice());
+    if errors {
+        return Err(TextureParseError::BadText);
+    }
+    Ok(result.into())
+}
+
+fn read_level(
+    reader: &mut Cursor<&[u8]>,
+    texture_ptr: u32,
+    info: &TextureInfo,
+    level: usize,
+) -> Result<Texture> {
+    let filename = read_filename(reader, info.filename_ptr)?;
+    let pixel_format = info.hardware_format()?;
 
-    // Read texture info
+    // Read pixel data
+    let width = info.width >> level;
+    let height = info.height >> level;
+    let offset = texture_decoder::get_mip_offset(info.width, info.height, pixel_format, level);
+    reader.seek(SeekFrom::Start(
+        (texture_ptr + info.texture_ptr) as u64 + offset as u64,
+    ))?;
+    let mut raw: Vec<u8> = vec![0; texture_decoder::get_level_size(width, height, pixel_format)];
+    reader.read_exact(&mut raw)?;
+    let pixel_data = texture_decoder::decode_pixel_data(&raw, width, height, pixel_format)?;
+    Ok(Texture {
+        filename,
+        width,
+        height,
+        pixel_data,
+        raw,
+        source_format: Some(TextureSourceFormat::Pica(pixel_format)),
+    })
+}
+
+fn read_texture_info(file: &[u8]) -> Result<(Header, Vec<TextureInfo>)> {
+    let mut reader = Cursor::new(file);
+    let header = Header::new(&mut reader)?;
     let mut texture_info: Vec<TextureInfo> = Vec::new();
     for _ in 0..header.texture_count {
         texture_info.push(TextureInfo::new(&mut reader)?);
     }
+    Ok((header, texture_info))
+}
+
+// Compares each texture's declared length against its dimensions and format.
+pub fn check_lengths(file: &[u8]) -> Result<Vec<TextureLengthMismatch>> {
+    let (_, texture_info) = read_texture_info(file)?;
+    let mut reader = Cursor::new(file);
+    let mut mismatches = Vec::new();
+    for info in &texture_info {
+        let filename = read_filename(&mut reader, info.filename_ptr)?;
+        mismatches.extend(texture_utils::check_texture_length(
+            &filename,
+            info.width,
+            info.height,
+            info.hardware_format()?,
+            info.mipmap_level as usize,
+            info.texture_length as usize,
+        ));
+    }
+    Ok(mismatches)
+}
+
+pub fn read_mip(file: &[u8], texture_index: usize, level: usize) -> Result<Texture> {
+    let mut reader = Cursor::new(file);
+    let header = Header::new(&mut reader)?;
+    if texture_index >= header.texture_count as usize {
+        return Err(TextureParseError::TextureIndexOutOfRange(texture_index));
+    }
+    reader.seek(SeekFrom::Current((texture_index * 0x20) as i64))?;
+    let info = TextureInfo::new(&mut reader)?;
+    if level >= info.mipmap_level.max(1) as usize {
+        return Err(TextureParseError::MipLevelOutOfRange(level));
+    }
+    read_level(&mut reader, header.texture_ptr, &info, level)
+}
+
+// Only the texture info table is read up front. Pixel data is decoded as the iterator advances.
+pub fn iter_textures(file: &[u8]) -> impl Iterator<Item = Result<Texture>> + '_ {
+    let (texture_ptr, texture_info, error) = match read_texture_info(file) {
+        Ok((header, texture_info)) => (header.texture_ptr, texture_info, None),
+        Err(err) => (0, Vec::new(), Some(Err(err))),
+    };
+    let mut reader = Cursor::new(file);
+    error.into_iter().chain(
+        texture_info
+            .into_iter()
+            .map(move |info| read_level(&mut reader, texture_ptr, &info, 0)),
+    )
+}
+
+pub fn read(file: &[u8]) -> Result<Vec<Texture>> {
+    iter_textures(file).collect()
+}
+
+pub fn read_lenient(file: &[u8]) -> (Vec<Texture>, Vec<TextureParseError>) {
+    texture_utils::collect_lenient(iter_textures(file))
+}
 
-    // Read texture
-    let mut texture: Vec<Texture> = Vec::new();
-    for i in 0..header.texture_count as usize {
-        // Read filename
-        reader.seek(SeekFrom::Start(texture_info[i].filename_ptr as u64))?;
-        let mut filename_buffer: Vec<u8> = Vec::new();
-        reader.read_until(0x0, &mut filename_buffer)?;
-        filename_buffer.pop(); // Get rid of the null terminator.
-        let (result, _, errors) = SHIFT_JIS.decode(filename_buffer.as_slice());
+const CTPK_MAGIC: u32 = 0x4B505443;
+
+pub fn write(textures: &[Texture], options: &TextureWriteOptions) -> Result<Vec<u8>> {
+    // Encode everything up front so we know the final layout.
+    let mut filenames: Vec<Vec<u8>> = Vec::new();
+    let mut encoded: Vec<Vec<u8>> = Vec::new();
+    for texture in textures {
+        let (name, _, errors) = SHIFT_JIS.encode(&texture.filename);
         if errors {
             return Err(TextureParseError::BadText);
         }
-        let filename: String = result.into();
-
-        // Read pixel data
-        reader.seek(SeekFrom::Start(
-            (header.texture_ptr + texture_info[i].texture_ptr) as u64,
-        ))?;
-        let mut pixel_data: Vec<u8> = vec![
-            0;
-            (texture_decoder::get_pixel_format_bpp(texture_info[i].pixel_format)
-                * texture_info[i].width as f32
-                * texture_info[i].height as f32) as usize
+        filenames.push(name.into_owned());
+        encoded.push(texture_encoder::encode_pixel_data(
+            &texture.pixel_data,
+            texture.width,
+            texture.height,
+            options.pixel_format,
+        )?);
+    }
+
+    let count = textures.len();
+    let info_start = 0x20;
+    let bitmap_sizes_start = info_start + count * 0x20;
+    let mut filename_table: Vec<u8> = Vec::new();
+    let mut filename_offsets: Vec<usize> = Vec::new();
+    let filenames_start = bitmap_sizes_start + count * 4;
+    for name in &filenames {
+        filename_offsets.push(filenames_start + filename_table.len());
+        filename_table.extend_from_slice(name);
+        filename_table.push(0);
+    }
+    pad_to(&mut filename_table, 4);
+    let hash_start = filenames_start + filename_table.len();
+    let short_info_start = hash_start + count * 8;
+    let texture_start = align(short_info_start + count * 8, 0x80);
+
+    let mut texture_data: Vec<u8> = Vec::new();
+    let mut texture_offsets: Vec<usize> = Vec::new();
+    for data in &encoded {
+        texture_offsets.push(texture_data.len());
+        texture_data.extend_from_slice(data);
+        pad_to(&mut texture_data, 0x80);
+    }
+
+    let mut out: Vec<u8> = Vec::new();
+    out.write_u32::<FileEndian>(CTPK_MAGIC)?;
+    out.write_u16::<FileEndian>(1)?;
+    out.write_u16::<FileEndian>(count as u16)?;
+    out.write_u32::<FileEndian>(texture_start as u32)?;
+    out.write_u32::<FileEndian>(texture_data.len() as u32)?;
+    out.write_u32::<FileEndian>(hash_start as u32)?;
+    out.write_u32::<FileEndian>(short_info_start as u32)?;
+    out.extend_from_slice(&[0; 8]);
+
+    for (i, texture) in textures.iter().enumerate() {
+        out.write_u32::<FileEndian>(filename_offsets[i] as u32)?;
+        out.write_u32::<FileEndian>(encoded[i].len() as u32)?;
+        out.write_u32::<FileEndian>(texture_offsets[i] as u32)?;
+        out.write_u32::<FileEndian>(options.pixel_format.to_u32())?;
+        out.write_u16::<FileEndian>(texture.width as u16)?;
+        out.write_u16::<FileEndian>(texture.height as u16)?;
+        out.write_u8(1)?; // Mipmap level
+        out.write_u8(0)?; // Texture type
+        out.write_u16::<FileEndian>(0)?; // Cube dir
+        out.write_u32::<FileEndian>((bitmap_sizes_start + i * 4) as u32)?;
+        out.write_u32::<FileEndian>(0)?; // File time
+    }
+    for data in &encoded {
+        out.write_u32::<FileEndian>(data.len() as u32)?;
+    }
+    out.extend_from_slice(&filename_table);
+
+    // Hashes are sorted so the game can binary search them.
+    let mut hashes: Vec<(u32, usize)> = filenames
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (crc32fast::hash(name), i))
+        .collect();
+    hashes.sort_unstable();
+    for (hash, index) in hashes {
+        out.write_u32::<FileEndian>(hash)?;
+        out.write_u32::<FileEndian>(index as u32)?;
+    }
+    for _ in 0..count {
+        out.write_u8(options.pixel_format.to_u32() as u8)?;
+        out.write_u8(1)?; // Mipmap count
+        out.write_u8(0)?; // Compressed
+        out.write_u8(0)?; // ETC1 quality
+        out.write_u32::<FileEndian>(0)?;
+    }
+    out.resize(texture_start, 0);
+    out.extend_from_slice(&texture_data);
+    Ok(out)
+}
+
+pub struct Ctpk;
+
+impl TextureContainer for Ctpk {
+    fn read(bytes: &[u8]) -> Result<Vec<Texture>> {
+        read(bytes)
+    }
+
+    fn write(textures: &[Texture], options: &TextureWriteOptions) -> Result<Vec<u8>> {
+        write(textures, options)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_header() {
+        let texture = Texture {
+            filename: "test".to_string(),
+            width: 8,
+            height: 8,
+            pixel_data: vec![0; 8 * 8 * 4],
+            ..Default::default()
+        };
+        let bytes = write(&[texture], &TextureWriteOptions::default()).unwrap();
+        let header = super::read_header(&bytes).unwrap();
+        assert_eq!(1, header.version);
+        assert_eq!(1, header.texture_count);
+        assert_eq!(0x80, header.texture_data_offset);
+        assert_eq!(8 * 8 * 4, header.texture_data_length);
+        assert!(super::read_header(&[0; 0x20]).is_err());
+    }
+
+    #[test]
+    fn unknown_pixel_format() {
+        let textures = vec![Texture {
+            filename: "test".to_string(),
+            width: 8,
+            height: 8,
+            pixel_data: vec![0; 8 * 8 * 4],
+            ..Default::default()
+        }];
+        let mut bytes = write(&textures, &TextureWriteOptions::default()).unwrap();
+        // Pixel format of the first texture info entry.
+        bytes[0x2C..0x30].copy_from_slice(&0x2Au32.to_le_bytes());
+        assert!(matches!(
+            read(&bytes),
+            Err(TextureParseError::UnknownPixelFormat(0x2A))
+        ));
+    }
+
+    #[test]
+    fn read_lenient_keeps_good_textures() {
+        let textures = vec![
+            Texture {
+                filename: "first".to_string(),
+                width: 8,
+                height: 8,
+                pixel_data: vec![0xFF; 8 * 8 * 4],
+                ..Default::default()
+            },
+            Texture {
+                filename: "second".to_string(),
+                width: 8,
+                height: 8,
+                pixel_data: vec![0x80; 8 * 8 * 4],
+                ..Default::default()
+            },
+        ];
+        let mut bytes = write(&textures, &TextureWriteOptions::default()).unwrap();
+        // Cut off the end of the second texture's pixel data.
+        bytes.truncate(bytes.len() - 0x10);
+        assert!(read(&bytes).is_err());
+
+        let (read_back, errors) = read_lenient(&bytes);
+        assert_eq!(1, read_back.len());
+        assert_eq!("first", read_back[0].filename);
+        assert_eq!(1, errors.len());
+
+        // An unknown format on one texture doesn't stop the other from being read.
+        let mut bytes = write(&textures, &TextureWriteOptions::default()).unwrap();
+        bytes[0x4C..0x50].copy_from_slice(&0x2Au32.to_le_bytes());
+        let (read_back, errors) = read_lenient(&bytes);
+        assert_eq!(1, read_back.len());
+        assert_eq!("first", read_back[0].filename);
+        assert!(matches!(
+            errors[..],
+            [TextureParseError::UnknownPixelFormat(0x2A)]
+        ));
+
+        let (read_back, errors) = read_lenient(&[0; 0x10]);
+        assert!(read_back.is_empty());
+        assert_eq!(1, errors.len());
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let textures = vec![
+            Texture {
+                filename: "first".to_string(),
+                width: 8,
+                height: 8,
+                pixel_data: (0..8 * 8 * 4).map(|i| i as u8).collect(),
+                ..Default::default()
+            },
+            Texture {
+                filename: "second".to_string(),
+                width: 16,
+                height: 8,
+                pixel_data: (0..16 * 8 * 4).map(|i| (i * 3) as u8).collect(),
+                ..Default::default()
+            },
+        ];
+        let bytes = Ctpk::write(&textures, &TextureWriteOptions::default()).unwrap();
+        assert_eq!(2, texture_count(&bytes).unwrap());
+        let read_back = Ctpk::read(&bytes).unwrap();
+        assert_eq!(2, read_back.len());
+        for (expected, actual) in textures.iter().zip(read_back.iter()) {
+            assert_eq!(expected.filename, actual.filename);
+            assert_eq!(expected.width, actual.width);
+            assert_eq!(expected.height, actual.height);
+            assert_eq!(expected.pixel_data, actual.pixel_data);
+            assert_eq!(
+                Some(TextureSourceFormat::Pica(HardwareTextureFormat::RGBA8)),
+                actual.source_format
+            );
+            let raw = texture_encoder::encode_pixel_data(
+                &expected.pixel_data,
+                expected.width,
+                expected.height,
+                HardwareTextureFormat::RGBA8,
+            )
+            .unwrap();
+            assert_eq!(raw, actual.raw);
+        }
+    }
+
+    #[test]
+    fn read_mip_level() {
+        let texture = Texture {
+            filename: "mips".to_string(),
+            width: 16,
+            height: 16,
+            pixel_data: (0..16 * 16 * 4).map(|i| i as u8).collect(),
+            ..Default::default()
+        };
+        let smaller = Texture {
+            filename: "mips".to_string(),
+            width: 8,
+            height: 8,
+            pixel_data: (0..8 * 8 * 4).map(|i| (i * 7) as u8).collect(),
+            ..Default::default()
+        };
+
+        // Build a two level texture by hand out of the single level writer output.
+        let mut bytes = write(
+            std::slice::from_ref(&texture),
+            &TextureWriteOptions::default(),
+        )
+        .unwrap();
+        let level_1 = texture_encoder::encode_pixel_data(
+            &smaller.pixel_data,
+            8,
+            8,
+            HardwareTextureFormat::RGBA8,
+        )
+        .unwrap();
+        bytes.extend_from_slice(&level_1);
+        bytes[0x34] = 2;
+
+        let level = read_mip(&bytes, 0, 1).unwrap();
+        assert_eq!(8, level.width);
+        assert_eq!(8, level.height);
+        assert_eq!(smaller.pixel_data, level.pixel_data);
+        assert_eq!(
+            texture.pixel_data,
+            read_mip(&bytes, 0, 0).unwrap().pixel_data
+        );
+        assert!(read_mip(&bytes, 0, 2).is_err());
+        assert!(read_mip(&bytes, 1, 0).is_err());
+    }
+
+    #[test]
+    fn width_before_height() {
+        let texture = Texture {
+            filename: "wide".to_string(),
+            width: 16,
+            height: 8,
+            pixel_data: vec![0; 16 * 8 * 4],
+            ..Default::default()
+        };
+        let bytes = write(
+            std::slice::from_ref(&texture),
+            &TextureWriteOptions::default(),
+        )
+        .unwrap();
+        assert_eq!([16, 0, 8, 0], bytes[0x30..0x34]);
+        assert_eq!(
+            16 * 8 * 4,
+            u32::from_le_bytes([bytes[0x24], bytes[0x25], bytes[0x26], bytes[0x27]])
+        );
+        let read_back = read(&bytes).unwrap();
+        assert_eq!(16, read_back[0].width);
+        assert_eq!(8, read_back[0].height);
+        assert!(check_lengths(&bytes).unwrap().is_empty());
+
+        // Claiming a second mip level makes the declared length too short.
+        let mut bytes = bytes;
+        bytes[0x34] = 2;
+        assert_eq!(
+            vec![TextureLengthMismatch {
+                filename: "wide".to_string(),
+                expected: (16 * 8 + 8 * 4) * 4,
+                declared: 16 * 8 * 4,
+            }],
+            check_lengths(&bytes).unwrap()
+        );
+    }
+
+    #[test]
+    fn iter_textures_is_lazy() {
+        let textures = vec![
+            Texture {
+                filename: "first".to_string(),
+                width: 8,
+                height: 8,
+                pixel_data: vec![1; 8 * 8 * 4],
+                ..Default::default()
+            },
+            Texture {
+                filename: "second".to_string(),
+                width: 8,
+                height: 8,
+                pixel_data: vec![2; 8 * 8 * 4],
+                ..Default::default()
+            },
         ];
-        reader.read_exact(&mut pixel_data)?;
-
-        let width = texture_info[i].width;
-        let height = texture_info[i].height;
-        let pixel_format = texture_info[i].pixel_format;
-        let pixel_data =
-            texture_decoder::decode_pixel_data(&pixel_data, width, height, pixel_format)?;
-        texture.push(Texture {
-            filename,
-            width,
-            height,
-            pixel_data,
-        });
+        let mut bytes = write(&textures, &TextureWriteOptions::default()).unwrap();
+
+        // Chop off the second texture's pixel data. The first should still decode.
+        bytes.truncate(bytes.len() - 0x80);
+        let mut iter = iter_textures(&bytes);
+        assert_eq!("first", iter.next().unwrap().unwrap().filename);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+
+        let mut iter = iter_textures(&[0; 4]);
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn write_unsupported_format() {
+        let textures = vec![Texture {
+            filename: "first".to_string(),
+            width: 8,
+            height: 8,
+            pixel_data: vec![0; 8 * 8 * 4],
+            ..Default::default()
+        }];
+        let options = TextureWriteOptions {
+            pixel_format: HardwareTextureFormat::ETC1,
+            ..Default::default()
+        };
+        assert!(Ctpk::write(&textures, &options).is_err());
     }
-    Ok(texture)
 }
@@ -1,6 +1,7 @@
-use crate::EncodedStringsError;
+use crate::padding::align;
+use crate::{EncodedStringsError, Endian};
 use byteorder::ReadBytesExt;
-use encoding_rs::{SHIFT_JIS, UTF_16LE};
+use encoding_rs::{SHIFT_JIS, UTF_16BE, UTF_16LE};
 use std::io::Cursor;
 
 type Result<T> = std::result::Result<T, EncodedStringsError>;
@@ -8,7 +9,11 @@ type Result<T> = std::result::Result<T, EncodedStringsError>;
 pub trait EncodedStringReader {
     fn read_shift_jis_string(&mut self) -> Result<String>;
 
-    fn read_utf_16_string(&mut self) -> Result<String>;
+    fn read_utf_16_string(&mut self) -> Result<String> {
+        self.read_utf_16_string_endian(Endian::Little)
+    }
+
+    fn read_utf_16_string_endian(&mut self, endian: Endian) -> Result<String>;
 }
 
 fn read_shift_jis_impl<F, E>(mut read_u8: F) -> Result<String>
@@ -32,7 +37,7 @@ where
     Ok(result.into())
 }
 
-fn read_utf_16_impl<F, E: std::fmt::Debug>(mut read_u8: F) -> Result<String>
+fn read_utf_16_impl<F, E: std::fmt::Debug>(mut read_u8: F, endian: Endian) -> Result<String>
 where
     F: FnMut() -> std::result::Result<u8, E>,
 {
@@ -52,11 +57,19 @@ where
         buffer.push(next_byte2);
     }
 
-    let (result, _enc, errors) = UTF_16LE.decode(buffer.as_slice());
+    // Decode without BOM sniffing so a stray BOM can't change the endian, then drop a leading one.
+    let encoding = match endian {
+        Endian::Little => UTF_16LE,
+        Endian::Big => UTF_16BE,
+    };
+    let (result, errors) = encoding.decode_without_bom_handling(buffer.as_slice());
     if errors {
         Err(EncodedStringsError::DecodingFailed("UTF-16".to_string()))
     } else {
-        Ok(result.into())
+        Ok(result
+            .strip_prefix('\u{FEFF}')
+            .unwrap_or(&result)
+            .to_string())
     }
 }
 
@@ -65,25 +78,21 @@ impl EncodedStringReader for Cursor<&[u8]> {
         read_shift_jis_impl(|| self.read_u8())
     }
 
-    fn read_utf_16_string(&mut self) -> Result<String> {
-        read_utf_16_impl(|| self.read_u8())
+    fn read_utf_16_string_endian(&mut self, endian: Endian) -> Result<String> {
+        read_utf_16_impl(|| self.read_u8(), endian)
     }
 }
 
 impl<'a> EncodedStringReader for crate::BinArchiveReader<'a> {
     fn read_shift_jis_string(&mut self) -> Result<String> {
         let result = read_shift_jis_impl(|| self.read_u8())?;
-        while self.tell() % 4 != 0 {
-            self.skip(1);
-        }
+        self.skip(align(self.tell(), 4) - self.tell());
         Ok(result)
     }
 
-    fn read_utf_16_string(&mut self) -> Result<String> {
-        let result = read_utf_16_impl(|| self.read_u8())?;
-        while self.tell() % 4 != 0 {
-            self.skip(1);
-        }
+    fn read_utf_16_string_endian(&mut self, endian: Endian) -> Result<String> {
+        let result = read_utf_16_impl(|| self.read_u8(), endian)?;
+        self.skip(align(self.tell(), 4) - self.tell());
         Ok(result)
     }
 }
@@ -100,8 +109,14 @@ pub fn to_shift_jis(string: &str) -> Result<Vec<u8>> {
     }
 }
 
-pub fn to_utf_16(string: &str) -> Result<Vec<u8>> {
-    let bytes: Vec<[u8; 2]> = string.encode_utf16().map(|x| x.to_le_bytes()).collect();
+pub fn to_utf_16_endian(string: &str, endian: Endian) -> Result<Vec<u8>> {
+    let bytes: Vec<[u8; 2]> = string
+        .encode_utf16()
+        .map(|x| match endian {
+            Endian::Little => x.to_le_bytes(),
+            Endian::Big => x.to_be_bytes(),
+        })
+        .collect();
     let mut buffer: Vec<u8> = Vec::new();
     for entry in bytes {
         buffer.push(entry[0]);
@@ -109,3 +124,42 @@ pub fn to_utf_16(string: &str) -> Result<Vec<u8>> {
     }
     Ok(buffer)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_utf_16_strips_bom() {
+        let mut cursor = Cursor::new(&[0xFF, 0xFE, 0x41, 0x00, 0x00, 0x00][..]);
+        assert_eq!("A", cursor.read_utf_16_string().unwrap());
+    }
+
+    #[test]
+    fn read_utf_16_big_endian() {
+        let mut cursor = Cursor::new(&[0x00, 0x41, 0x30, 0x42, 0x00, 0x00][..]);
+        assert_eq!(
+            "Aあ",
+            cursor.read_utf_16_string_endian(Endian::Big).unwrap()
+        );
+    }
+
+    #[test]
+    fn read_utf_16_ignores_swapped_bom() {
+        // A byte-swapped BOM shouldn't switch the rest of the string to big endian.
+        let mut cursor = Cursor::new(&[0xFE, 0xFF, 0x41, 0x00, 0x00, 0x00][..]);
+        assert_eq!("\u{FFFE}A", cursor.read_utf_16_string().unwrap());
+    }
+
+    #[test]
+    fn to_utf_16_little_endian() {
+        let bytes = to_utf_16_endian("Aあ", Endian::Little).unwrap();
+        assert_eq!(vec![0x41, 0x00, 0x42, 0x30], bytes);
+    }
+
+    #[test]
+    fn to_utf_16_big_endian() {
+        let bytes = to_utf_16_endian("Aあ", Endian::Big).unwrap();
+        assert_eq!(vec![0x00, 0x41, 0x30, 0x42], bytes);
+    }
+}
@@ -7,7 +7,7 @@ use crate::EndianAwareIOError;
 
 type Result<T> = std::result::Result<T, EndianAwareIOError>;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Endian {
     Little,
     Big,
@@ -41,6 +41,9 @@ pub enum ArchiveError {
     #[error("Index '{1}' is out of bounds for label bucket of size '{0}'.")]
     LabelIndexOutOfBounds(usize, usize),
 
+    #[error("Label '{0}' does not exist.")]
+    MissingLabel(String),
+
     #[error(transparent)]
     IOError(#[from] std::io::Error),
 
@@ -77,6 +80,9 @@ pub enum LayeredFilesystemError {
     #[error("Filesystem contains no writeable layers.")]
     NoWriteableLayers,
 
+    #[error("Filesystem was opened read-only.")]
+    ReadOnly,
+
     #[error("File '{0}' does not exist. Attempted to find it using the following paths: '[{1}]'")]
     FileNotFound(String, String),
 
@@ -167,8 +173,38 @@ pub enum TextureDecodeError {
     #[error("Block size is larger than texture dimensions.")]
     BadBlockSize,
 
-    #[error("Texture dimensions are not consistent with input size.")]
-    BadDimensions,
+    #[error("Pixel ({x}, {y}) is outside of the {width}x{height} texture.")]
+    PixelOutOfBounds {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+
+    #[error("Texture dimensions {width}x{height} are invalid.")]
+    InvalidDimensions { width: usize, height: usize },
+
+    #[error(
+        "Region {width}x{height} at ({x}, {y}) is outside of the {texture_width}x{texture_height} texture."
+    )]
+    RegionOutOfBounds {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+        texture_width: usize,
+        texture_height: usize,
+    },
+
+    #[error(
+        "Texture dimensions {width}x{height} are not consistent with input size: expected {expected_len} bytes, got {actual_len}."
+    )]
+    BadDimensions {
+        width: usize,
+        height: usize,
+        expected_len: usize,
+        actual_len: usize,
+    },
 
     #[error(transparent)]
     IOError(#[from] std::io::Error),
@@ -188,6 +224,18 @@ pub enum TextureParseError {
     #[error("Parser error: {0}")]
     ParserError(String),
 
+    #[error("Writing {0} containers is not supported.")]
+    WriteNotSupported(String),
+
+    #[error("Texture index {0} is out of range.")]
+    TextureIndexOutOfRange(usize),
+
+    #[error("Mipmap level {0} is out of range.")]
+    MipLevelOutOfRange(usize),
+
+    #[error("Unknown pixel format 0x{0:X}.")]
+    UnknownPixelFormat(u32),
+
     #[error(transparent)]
     IOError(#[from] std::io::Error),
 
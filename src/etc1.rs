@@ -48,10 +48,115 @@ fn complement(input: u8, bits: u8) -> u8 {
     }
 }
 
+// Decodes one 4x4 block to row-major RGBA. ETC1A4 blocks are 16 bytes with the 4-bit alpha
+// values first, plain ETC1 blocks are the 8 color bytes alone.
+pub fn decode_block(block: &[u8], with_alpha: bool) -> Result<[u8; 64]> {
+    let modifiers = get_etc_modifiers_table();
+    let mut cursor = Cursor::new(block);
+    let alphas = if with_alpha {
+        cursor.read_u64::<LittleEndian>()?
+    } else {
+        0xFFFFFFFFFFFFFFFF
+    };
+    let pixels = cursor.read_u64::<LittleEndian>()?;
+
+    let differential = (pixels >> ETC_DIFFERENTIAL_BIT) & 1 == 1;
+    let horizontal = (pixels >> ETC_ORIENTATION_BIT) & 1 == 1;
+    let table1_index = (pixels >> ETC_TABLE1_OFFSET) & 7;
+    let table2_index = (pixels >> ETC_TABLE2_OFFSET) & 7;
+    let table1 = &modifiers[table1_index as usize];
+    let table2 = &modifiers[table2_index as usize];
+
+    let mut color1: Vec<u8> = vec![0, 0, 0];
+    let mut color2: Vec<u8> = vec![0, 0, 0];
+    if differential {
+        let r = ((pixels >> ETC_DIFF_RED1_OFFSET) & 0x1F) as u8;
+        let g = ((pixels >> ETC_DIFF_GREEN1_OFFSET) & 0x1F) as u8;
+        let b = ((pixels >> ETC_DIFF_BLUE1_OFFSET) & 0x1F) as u8;
+
+        color1[0] = (r << 3) | ((r >> 2) & 7);
+        color1[1] = (g << 3) | ((g >> 2) & 7);
+        color1[2] = (b << 3) | ((b >> 2) & 7);
+
+        let r_comp_input = ((pixels >> ETC_RED2_OFFSET) & 7) as u8;
+        let g_comp_input = ((pixels >> ETC_GREEN2_OFFSET) & 7) as u8;
+        let b_comp_input = ((pixels >> ETC_BLUE2_OFFSET) & 7) as u8;
+
+        let r2 = r + complement(r_comp_input, 3);
+        let g2 = g + complement(g_comp_input, 3);
+        let b2 = b + complement(b_comp_input, 3);
+
+        color2[0] = (r2 << 3) | ((r2 >> 2) & 7);
+        color2[1] = (g2 << 3) | ((g2 >> 2) & 7);
+        color2[2] = (b2 << 3) | ((b2 >> 2) & 7);
+    } else {
+        color1[0] = (((pixels >> ETC_INDIV_RED1_OFFSET) & 0xF) * 0x11) as u8;
+        color1[1] = (((pixels >> ETC_INDIV_GREEN1_OFFSET) & 0xF) * 0x11) as u8;
+        color1[2] = (((pixels >> ETC_INDIV_BLUE1_OFFSET) & 0xF) * 0x11) as u8;
+
+        color2[0] = (((pixels >> ETC_RED2_OFFSET) & 0xF) * 0x11) as u8;
+        color2[1] = (((pixels >> ETC_GREEN2_OFFSET) & 0xF) * 0x11) as u8;
+        color2[2] = (((pixels >> ETC_BLUE2_OFFSET) & 0xF) * 0x11) as u8;
+    }
+
+    let amounts = pixels & 0xFFFF;
+    let signs = (pixels >> 16) & 0xFFFF;
+
+    let mut output = [0u8; 64];
+    for pixel_y in 0..4 {
+        for pixel_x in 0..4 {
+            // Pixel indices run down columns rather than across rows.
+            let offset = pixel_x * 4 + pixel_y;
+
+            let table = if horizontal {
+                if pixel_y < 2 {
+                    table1
+                } else {
+                    table2
+                }
+            } else if pixel_x < 2 {
+                table1
+            } else {
+                table2
+            };
+            let color = if horizontal {
+                if pixel_y < 2 {
+                    &color1
+                } else {
+                    &color2
+                }
+            } else if pixel_x < 2 {
+                &color1
+            } else {
+                &color2
+            };
+
+            let sign = (signs >> offset) & 1;
+            let amount = if sign == 1 {
+                -table[((amounts >> offset) & 1) as usize]
+            } else {
+                table[((amounts >> offset) & 1) as usize]
+            };
+
+            let red = (color[0] as i32 + amount).min(0xFF).max(0) as u8;
+            let green = (color[1] as i32 + amount).min(0xFF).max(0) as u8;
+            let blue = (color[2] as i32 + amount).min(0xFF).max(0) as u8;
+            let alpha = (((alphas >> (offset * 4)) & 0xF) * 0x11) as u8;
+            let pixel_pos = (pixel_y * 4 + pixel_x) * 4;
+            output[pixel_pos..pixel_pos + 4].copy_from_slice(&[red, green, blue, alpha]);
+        }
+    }
+    Ok(output)
+}
+
 pub fn decode(pixel_data: &[u8], width: usize, height: usize, with_alpha: bool) -> Result<Vec<u8>> {
     let mut bmp: Vec<u8> = Vec::new();
     bmp.resize(4 * width * height, 0);
-    let modifiers = get_etc_modifiers_table();
+    let block_size = if with_alpha {
+        ETC1A4_BLOCK_SIZE
+    } else {
+        ETC1_BLOCK_SIZE
+    };
 
     let tile_width: usize = 1 << (((width as f64) / 8.0).ceil().log2() as usize);
     let tile_height: usize = 1 << (((height as f64) / 8.0).ceil().log2() as usize);
@@ -60,118 +165,20 @@ pub fn decode(pixel_data: &[u8], width: usize, height: usize, with_alpha: bool)
         for tile_x in 0..tile_width {
             for block_y in 0..2 {
                 for block_x in 0..2 {
-                    let data_pos = pos;
-                    pos += if with_alpha {
-                        ETC1A4_BLOCK_SIZE
-                    } else {
-                        ETC1_BLOCK_SIZE
-                    };
-
-                    let block = if with_alpha {
-                        &pixel_data[data_pos..data_pos + ETC1A4_BLOCK_SIZE]
-                    } else {
-                        &pixel_data[data_pos..data_pos + ETC1_BLOCK_SIZE]
-                    };
-                    let mut cursor = Cursor::new(block);
-                    let alphas = if with_alpha {
-                        cursor.read_u64::<LittleEndian>()?
-                    } else {
-                        0xFFFFFFFFFFFFFFFF
-                    };
-                    let pixels = cursor.read_u64::<LittleEndian>()?;
-
-                    let differential = (pixels >> ETC_DIFFERENTIAL_BIT) & 1 == 1;
-                    let horizontal = (pixels >> ETC_ORIENTATION_BIT) & 1 == 1;
-                    let table1_index = (pixels >> ETC_TABLE1_OFFSET) & 7;
-                    let table2_index = (pixels >> ETC_TABLE2_OFFSET) & 7;
-                    let table1 = &modifiers[table1_index as usize];
-                    let table2 = &modifiers[table2_index as usize];
-
-                    let mut color1: Vec<u8> = vec![0, 0, 0];
-                    let mut color2: Vec<u8> = vec![0, 0, 0];
-                    if differential {
-                        let r = ((pixels >> ETC_DIFF_RED1_OFFSET) & 0x1F) as u8;
-                        let g = ((pixels >> ETC_DIFF_GREEN1_OFFSET) & 0x1F) as u8;
-                        let b = ((pixels >> ETC_DIFF_BLUE1_OFFSET) & 0x1F) as u8;
-
-                        color1[0] = (r << 3) | ((r >> 2) & 7);
-                        color1[1] = (g << 3) | ((g >> 2) & 7);
-                        color1[2] = (b << 3) | ((b >> 2) & 7);
-
-                        let r_comp_input = ((pixels >> ETC_RED2_OFFSET) & 7) as u8;
-                        let g_comp_input = ((pixels >> ETC_GREEN2_OFFSET) & 7) as u8;
-                        let b_comp_input = ((pixels >> ETC_BLUE2_OFFSET) & 7) as u8;
-
-                        let r2 = r + complement(r_comp_input, 3);
-                        let g2 = g + complement(g_comp_input, 3);
-                        let b2 = b + complement(b_comp_input, 3);
-
-                        color2[0] = (r2 << 3) | ((r2 >> 2) & 7);
-                        color2[1] = (g2 << 3) | ((g2 >> 2) & 7);
-                        color2[2] = (b2 << 3) | ((b2 >> 2) & 7);
-                    } else {
-                        color1[0] = (((pixels >> ETC_INDIV_RED1_OFFSET) & 0xF) * 0x11) as u8;
-                        color1[1] = (((pixels >> ETC_INDIV_GREEN1_OFFSET) & 0xF) * 0x11) as u8;
-                        color1[2] = (((pixels >> ETC_INDIV_BLUE1_OFFSET) & 0xF) * 0x11) as u8;
-
-                        color2[0] = (((pixels >> ETC_RED2_OFFSET) & 0xF) * 0x11) as u8;
-                        color2[1] = (((pixels >> ETC_GREEN2_OFFSET) & 0xF) * 0x11) as u8;
-                        color2[2] = (((pixels >> ETC_BLUE2_OFFSET) & 0xF) * 0x11) as u8;
-                    }
-
-                    let amounts = pixels & 0xFFFF;
-                    let signs = (pixels >> 16) & 0xFFFF;
-
+                    let block = &pixel_data[pos..pos + block_size];
+                    pos += block_size;
+                    let decoded = decode_block(block, with_alpha)?;
                     for pixel_y in 0..4 {
                         for pixel_x in 0..4 {
                             let x = pixel_x + (block_x * 4) + (tile_x * 8);
                             let y = pixel_y + (block_y * 4) + (tile_y * 8);
-
                             if x >= width || y >= height {
                                 continue;
                             }
-
-                            let offset = pixel_x * 4 + pixel_y;
-
-                            let table = if horizontal {
-                                if pixel_y < 2 {
-                                    table1
-                                } else {
-                                    table2
-                                }
-                            } else if pixel_x < 2 {
-                                table1
-                            } else {
-                                table2
-                            };
-                            let color = if horizontal {
-                                if pixel_y < 2 {
-                                    &color1
-                                } else {
-                                    &color2
-                                }
-                            } else if pixel_x < 2 {
-                                &color1
-                            } else {
-                                &color2
-                            };
-
-                            let sign = (signs >> offset) & 1;
-                            let amount = if sign == 1 {
-                                -table[((amounts >> offset) & 1) as usize]
-                            } else {
-                                table[((amounts >> offset) & 1) as usize]
-                            };
-
-                            let red = (color[0] as i32 + amount).min(0xFF).max(0) as u8;
-                            let green = (color[1] as i32 + amount).min(0xFF).max(0) as u8;
-                            let blue = (color[2] as i32 + amount).min(0xFF).max(0) as u8;
-                            let alpha = (((alphas >> (offset * 4)) & 0xF) * 0x11) as u8;
+                            let block_pos = (pixel_y * 4 + pixel_x) * 4;
                             let pixel_pos = (y * width + x) * 4;
-                            bmp[pixel_pos] = red;
-                            bmp[pixel_pos + 1] = green;
-                            bmp[pixel_pos + 2] = blue;
-                            bmp[pixel_pos + 3] = alpha;
+                            bmp[pixel_pos..pixel_pos + 4]
+                                .copy_from_slice(&decoded[block_pos..block_pos + 4]);
                         }
                     }
                 }
@@ -180,3 +187,28 @@ pub fn decode(pixel_data: &[u8], width: usize, height: usize, with_alpha: bool)
     }
     Ok(bmp)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_block_individual_mode() {
+        // Individual mode, both halves 0x8 (0x88 per channel), table 0, all modifiers +2.
+        let block = 0x8888_8800_0000_0000u64.to_le_bytes();
+        let decoded = decode_block(&block, false).unwrap();
+        assert!(decoded
+            .chunks(4)
+            .all(|pixel| pixel == [0x8A, 0x8A, 0x8A, 0xFF]));
+    }
+
+    #[test]
+    fn decode_block_with_alpha() {
+        let mut block = 0x0000_0000_0000_000Fu64.to_le_bytes().to_vec();
+        block.extend(&0x8888_8800_0000_0000u64.to_le_bytes());
+        let decoded = decode_block(&block, true).unwrap();
+        assert_eq!(0xFF, decoded[3]);
+        assert!(decoded[4..].chunks(4).all(|pixel| pixel[3] == 0));
+        assert!(decode_block(&block[..8], true).is_err());
+    }
+}
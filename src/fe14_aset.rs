@@ -1,4 +1,4 @@
-use crate::{ArchiveError, BinArchive, BinArchiveReader, BinArchiveWriter, Endian};
+use crate::{ArchiveError, BinArchive, BinArchiveReader, BinArchiveWriter, Endian, FromArchive};
 
 type Result<T> = std::result::Result<T, ArchiveError>;
 
@@ -370,10 +370,10 @@ impl FE14ASet {
 
             // Allocate space and write.
             writer.allocate_at_end((flags_to_write + strings_to_write + 1) * 4);
-            if let Some(label) = &set[0] {
-                writer.write_label(label)?;
+            match &set[0] {
+                Some(label) => writer.write_labeled_u32(label, main_flags)?,
+                None => writer.write_u32(main_flags)?,
             }
-            writer.write_u32(main_flags)?;
             for i in 0..8 {
                 if compiled_flags[i] != 0 {
                     writer.write_u32(compiled_flags[i])?;
@@ -390,6 +390,12 @@ impl FE14ASet {
     }
 }
 
+impl FromArchive for FE14ASet {
+    fn from_archive(archive: &BinArchive) -> Result<Self> {
+        FE14ASet::from_archive(archive)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
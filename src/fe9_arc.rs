@@ -2,6 +2,7 @@ use std::io::{Cursor, Read};
 use byteorder::{ReadBytesExt, BigEndian};
 use indexmap::IndexMap;
 use crate::encoded_strings::{EncodedStringReader, to_shift_jis};
+use crate::padding::pad_to_with_offset;
 
 type Result<T> = std::result::Result<T, crate::ArchiveError>;
 
@@ -62,9 +63,7 @@ pub fn serialize(contents: &IndexMap<String, Vec<u8>>) -> Result<Vec<u8>> {
         raw_text.extend(raw);
         raw_text.push(0);
     }
-    while (header_length + raw_text.len()) % PADDING_BOUNDARY != 0 {
-        raw_text.push(0);
-    }
+    pad_to_with_offset(&mut raw_text, header_length, PADDING_BOUNDARY);
 
     //Compute file addresses.
     let mut next_file_address = header_length + raw_text.len();
@@ -73,9 +72,11 @@ pub fn serialize(contents: &IndexMap<String, Vec<u8>>) -> Result<Vec<u8>> {
     for raw_file in contents.values() {
         file_info.push((next_file_address, raw_file.len()));
         raw_files.extend(raw_file);
-        while (header_length + raw_text.len() + raw_files.len()) % PADDING_BOUNDARY != 0 {
-            raw_files.push(0);
-        }
+        pad_to_with_offset(
+            &mut raw_files,
+            header_length + raw_text.len(),
+            PADDING_BOUNDARY,
+        );
         next_file_address = header_length + raw_text.len() + raw_files.len();
     }
 
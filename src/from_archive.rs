@@ -0,0 +1,8 @@
+use crate::{ArchiveError, BinArchive};
+
+type Result<T> = std::result::Result<T, ArchiveError>;
+
+// Typed models that can be parsed from a BinArchive on their own.
+pub trait FromArchive: Sized {
+    fn from_archive(archive: &BinArchive) -> Result<Self>;
+}
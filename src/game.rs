@@ -10,3 +10,34 @@ pub enum Game {
     FE14,
     FE15,
 }
+
+impl Game {
+    // Extension conventionally appended to compressed files for this game.
+    pub fn compressed_extension(&self) -> &'static str {
+        match self {
+            Game::FE9 | Game::FE11 | Game::FE12 => ".cmp",
+            Game::FE10 => ".cms",
+            Game::FE13 | Game::FE14 | Game::FE15 => ".lz",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{LZ10CompressionFormat, LZ13CompressionFormat};
+
+    #[test]
+    fn compressed_extension() {
+        let lz10 = LZ10CompressionFormat {};
+        for game in &[Game::FE9, Game::FE10, Game::FE11, Game::FE12] {
+            let path = format!("Data{}", game.compressed_extension());
+            assert!(lz10.is_compressed_filename(&path));
+        }
+        let lz13 = LZ13CompressionFormat {};
+        for game in &[Game::FE13, Game::FE14, Game::FE15] {
+            let path = format!("GameData.bin{}", game.compressed_extension());
+            assert!(lz13.is_compressed_filename(&path));
+        }
+    }
+}
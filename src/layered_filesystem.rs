@@ -5,23 +5,37 @@ use crate::text_archive::TextArchiveFormat;
 use crate::tpl::Tpl;
 use crate::{
     arc, bch, cgfx, ctpk, fe9_arc, Endian, FE10PathLocalizer, FE9PathLocalizer,
-    LZ10CompressionFormat, LayeredFilesystemError, TextArchive, Texture,
+    LZ10CompressionFormat, LayeredFilesystemError, NoOpCompressionFormat, NoOpPathLocalizer,
+    TextArchive, Texture,
 };
 use crate::{
-    BinArchive, CompressionFormat, FE13PathLocalizer, FE14PathLocalizer, FE15PathLocalizer, Game,
-    LZ13CompressionFormat, Language, PathLocalizer,
+    BinArchive, CompressionFormat, FE13PathLocalizer, FE14PathLocalizer, FE15PathLocalizer,
+    FromArchive, Game, LZ13CompressionFormat, Language, PathLocalizer,
 };
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
 
 type Result<T> = std::result::Result<T, LayeredFilesystemError>;
 
+// Decompressed contents keyed by (layer root, path), tagged with the mtime they were read at.
+type ReadCache = Mutex<HashMap<(String, String), (SystemTime, Vec<u8>)>>;
+
 #[derive(Debug, Clone)]
 pub enum FileSystemLayer {
     Directory(String),
 }
 
+// Listed paths use forward slashes on every platform so they can be passed straight back in.
+fn layer_relative_path(path: &Path, layer_str: &str) -> String {
+    path.display()
+        .to_string()
+        .replace(layer_str, "")
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
+
 impl FileSystemLayer {
     pub fn read(&self, path: &str) -> Result<Vec<u8>> {
         match self {
@@ -55,6 +69,16 @@ impl FileSystemLayer {
     }
 
     pub fn list(&self, path: &str, glob: Option<&str>) -> Result<Vec<String>> {
+        self.list_with_depth(path, glob, None)
+    }
+
+    // max_depth counts path components below path, so Some(1) lists only its direct children.
+    pub fn list_with_depth(
+        &self,
+        path: &str,
+        glob: Option<&str>,
+        max_depth: Option<usize>,
+    ) -> Result<Vec<String>> {
         match self {
             FileSystemLayer::Directory(p) => {
                 // TODO: Clean up this mess.
@@ -63,15 +87,22 @@ impl FileSystemLayer {
                 layer_str.push(std::path::MAIN_SEPARATOR);
                 let full_path = Path::new(p).join(path);
                 if full_path.exists() {
-                    let mut canonical =
-                        full_path.normalize()?.into_path_buf().display().to_string();
+                    let base = full_path.normalize()?.into_path_buf();
+                    let mut canonical = base.display().to_string();
                     canonical.push(std::path::MAIN_SEPARATOR);
 
                     let pattern = if let Some(p) = glob { p } else { "**/*" };
                     let pattern = format!("{}{}", canonical, pattern);
+                    let within_depth = |p: &PathBuf| {
+                        max_depth.is_none_or(|depth| {
+                            p.strip_prefix(&base)
+                                .is_ok_and(|relative| relative.components().count() <= depth)
+                        })
+                    };
                     Ok(glob::glob(&pattern)?
                         .filter_map(|r| r.ok())
-                        .map(|p| p.display().to_string().replace(&layer_str, ""))
+                        .filter(within_depth)
+                        .map(|p| layer_relative_path(&p, &layer_str))
                         .collect())
                 } else {
                     Ok(Default::default())
@@ -95,7 +126,7 @@ impl FileSystemLayer {
                     Ok(glob::glob(&pattern)?
                         .filter_map(|r| r.ok())
                         .filter(|p| p.is_dir())
-                        .map(|p| p.display().to_string().replace(&layer_str, ""))
+                        .map(|p| layer_relative_path(&p, &layer_str))
                         .collect())
                 } else {
                     Ok(Default::default())
@@ -122,6 +153,14 @@ impl FileSystemLayer {
         }
     }
 
+    pub fn modified(&self, path: &str) -> Option<SystemTime> {
+        match self {
+            FileSystemLayer::Directory(p) => std::fs::metadata(Path::new(p).join(path))
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+
     pub fn resolve(&self, path: &str) -> Option<PathBuf> {
         match self {
             FileSystemLayer::Directory(p) => {
@@ -138,14 +177,28 @@ impl FileSystemLayer {
     }
 }
 
+// Which layer wins when a file exists in more than one. Writes always go to the top layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadPolicy {
+    // The last layer given takes precedence, so later layers overlay earlier ones.
+    #[default]
+    TopWins,
+    // The first layer given takes precedence.
+    BottomWins,
+}
+
 pub struct LayeredFilesystem {
     layers: Vec<FileSystemLayer>,
     compression_format: CompressionFormat,
     path_localizer: PathLocalizer,
-    game: Game,
+    game: Option<Game>,
     language: Language,
     endian: Endian,
     text_archive_format: TextArchiveFormat,
+    read_only: bool,
+    read_policy: ReadPolicy,
+    // None when caching is off.
+    cache: Option<ReadCache>,
 }
 
 impl Clone for LayeredFilesystem {
@@ -158,10 +211,24 @@ impl Clone for LayeredFilesystem {
             language: self.language,
             endian: self.endian,
             text_archive_format: self.text_archive_format,
+            read_only: self.read_only,
+            read_policy: self.read_policy,
+            cache: self.cache.as_ref().map(|_| Mutex::new(HashMap::new())),
         }
     }
 }
 
+fn canonicalize_layers(layers: &[String]) -> Result<Vec<FileSystemLayer>> {
+    let mut canonical_layers = Vec::new();
+    for layer in layers {
+        let path = Path::new(layer);
+        canonical_layers.push(FileSystemLayer::Directory(
+            path.normalize()?.into_path_buf().display().to_string(),
+        ));
+    }
+    Ok(canonical_layers)
+}
+
 fn texture_vec_to_map(textures: Vec<Texture>) -> HashMap<String, Texture> {
     textures
         .into_iter()
@@ -209,26 +276,58 @@ impl LayeredFilesystem {
             _ => TextArchiveFormat::Unicode,
         };
 
-        let mut canonical_layers = Vec::new();
-        for layer in &layers {
-            let path = Path::new(layer);
-            canonical_layers.push(FileSystemLayer::Directory(
-                path.normalize()?.into_path_buf().display().to_string(),
-            ));
-        }
-
         Ok(LayeredFilesystem {
-            layers: canonical_layers,
+            layers: canonicalize_layers(&layers)?,
             compression_format,
             path_localizer,
-            game,
+            game: Some(game),
             language,
             endian,
             text_archive_format,
+            read_only: false,
+            read_policy: ReadPolicy::TopWins,
+            cache: None,
+        })
+    }
+
+    // Same as new, but every write operation fails with ReadOnly.
+    pub fn new_read_only(layers: Vec<String>, language: Language, game: Game) -> Result<Self> {
+        let mut fs = LayeredFilesystem::new(layers, language, game)?;
+        fs.read_only = true;
+        Ok(fs)
+    }
+
+    // For data that doesn't belong to a particular game: no localization or compression.
+    pub fn new_raw(layers: Vec<String>) -> Result<Self> {
+        if layers.is_empty() {
+            return Err(LayeredFilesystemError::NoLayers);
+        }
+        Ok(LayeredFilesystem {
+            layers: canonicalize_layers(&layers)?,
+            compression_format: CompressionFormat::NoOp(NoOpCompressionFormat {}),
+            path_localizer: PathLocalizer::NoOp(NoOpPathLocalizer {}),
+            game: None,
+            language: Language::EnglishNA,
+            endian: Endian::Little,
+            text_archive_format: TextArchiveFormat::Unicode,
+            read_only: false,
+            read_policy: ReadPolicy::TopWins,
+            cache: None,
         })
     }
 
     pub fn list(&self, path: &str, glob: Option<&str>, localized: bool) -> Result<Vec<String>> {
+        self.list_with_depth(path, glob, None, localized)
+    }
+
+    // Same as list, but stops max_depth levels below path. Some(1) lists only its direct children.
+    pub fn list_with_depth(
+        &self,
+        path: &str,
+        glob: Option<&str>,
+        max_depth: Option<usize>,
+        localized: bool,
+    ) -> Result<Vec<String>> {
         let path = if localized {
             self.path_localizer.localize(path, &self.language)?
         } else {
@@ -236,14 +335,20 @@ impl LayeredFilesystem {
         };
         let mut result = HashSet::new();
         for layer in &self.layers {
-            result.extend(layer.list(&path, glob)?);
+            result.extend(layer.list_with_depth(&path, glob, max_depth)?);
         }
         let mut result: Vec<String> = result.into_iter().collect();
         result.sort();
         Ok(result)
     }
 
-    pub fn subdirectories(&self, path: &str, localized: bool) -> Result<Vec<String>> {
+    // Same as list, but directories are filtered out.
+    pub fn list_files(
+        &self,
+        path: &str,
+        glob: Option<&str>,
+        localized: bool,
+    ) -> Result<Vec<String>> {
         let path = if localized {
             self.path_localizer.localize(path, &self.language)?
         } else {
@@ -251,31 +356,49 @@ impl LayeredFilesystem {
         };
         let mut result = HashSet::new();
         for layer in &self.layers {
-            result.extend(layer.subdirectories(&path)?);
+            result.extend(
+                layer
+                    .list(&path, glob)?
+                    .into_iter()
+                    .filter(|entry| layer.file_exists(entry)),
+            );
         }
         let mut result: Vec<String> = result.into_iter().collect();
         result.sort();
         Ok(result)
     }
 
-    pub fn read(&self, path: &str, localized: bool) -> Result<Vec<u8>> {
-        let actual_path = if localized {
+    pub fn subdirectories(&self, path: &str, localized: bool) -> Result<Vec<String>> {
+        let path = if localized {
             self.path_localizer.localize(path, &self.language)?
         } else {
             path.to_string()
         };
+        let mut result = HashSet::new();
+        for layer in &self.layers {
+            result.extend(layer.subdirectories(&path)?);
+        }
+        let mut result: Vec<String> = result.into_iter().collect();
+        result.sort();
+        Ok(result)
+    }
+
+    // Layers in the order reads should search them.
+    fn read_order(&self) -> Vec<&FileSystemLayer> {
+        match self.read_policy {
+            ReadPolicy::TopWins => self.layers.iter().rev().collect(),
+            ReadPolicy::BottomWins => self.layers.iter().collect(),
+        }
+    }
+
+    fn read_raw(&self, actual_path: String) -> Result<Vec<u8>> {
         let mut attempted_paths: Vec<String> = Vec::new();
-        for layer in self.layers.iter().rev() {
+        for layer in self.read_order() {
             attempted_paths.push(layer.root().to_string());
             if layer.file_exists(&actual_path) {
-                let bytes = layer.read(&actual_path).map_err(|err| {
+                return layer.read(&actual_path).map_err(|err| {
                     LayeredFilesystemError::ReadError(actual_path, err.to_string())
-                })?;
-                if self.compression_format.is_compressed_filename(path) {
-                    return Ok(self.compression_format.decompress(&bytes)?);
-                } else {
-                    return Ok(bytes);
-                }
+                });
             }
         }
         Err(LayeredFilesystemError::FileNotFound(
@@ -284,6 +407,111 @@ impl LayeredFilesystem {
         ))
     }
 
+    pub fn read(&self, path: &str, localized: bool) -> Result<Vec<u8>> {
+        let actual_path = if localized {
+            self.path_localizer.localize(path, &self.language)?
+        } else {
+            path.to_string()
+        };
+        // Decide on compression from the path that's actually read, in case localization changes it.
+        let compressed = self.compression_format.is_compressed_filename(&actual_path);
+        let cache = match &self.cache {
+            Some(cache) => cache,
+            None => return self.read_decompressed(actual_path, compressed),
+        };
+        let source = self.read_order().into_iter().find_map(|layer| {
+            if layer.file_exists(&actual_path) {
+                layer
+                    .modified(&actual_path)
+                    .map(|modified| (layer.root().to_string(), modified))
+            } else {
+                None
+            }
+        });
+        let (root, modified) = match source {
+            Some(source) => source,
+            None => return self.read_decompressed(actual_path, compressed),
+        };
+
+        let key = (root, actual_path.clone());
+        if let Ok(cache) = cache.lock() {
+            if let Some((cached_at, bytes)) = cache.get(&key) {
+                if *cached_at == modified {
+                    return Ok(bytes.clone());
+                }
+            }
+        }
+        let bytes = self.read_decompressed(actual_path, compressed)?;
+        if let Ok(mut cache) = cache.lock() {
+            cache.insert(key, (modified, bytes.clone()));
+        }
+        Ok(bytes)
+    }
+
+    fn read_decompressed(&self, actual_path: String, compressed: bool) -> Result<Vec<u8>> {
+        let bytes = self.read_raw(actual_path)?;
+        if compressed {
+            Ok(self.compression_format.decompress(&bytes)?)
+        } else {
+            Ok(bytes)
+        }
+    }
+
+    // Off by default. When on, read reuses decompressed contents until a file's mtime changes.
+    pub fn set_cache_enabled(&mut self, enabled: bool) {
+        self.cache = if enabled {
+            Some(Mutex::new(HashMap::new()))
+        } else {
+            None
+        };
+    }
+
+    // Writes can land within the mtime's resolution, so drop the entry rather than trust it.
+    fn forget_cached(&self, layer: &FileSystemLayer, actual_path: &str) {
+        if let Some(Ok(mut cache)) = self.cache.as_ref().map(|cache| cache.lock()) {
+            cache.remove(&(layer.root().to_string(), actual_path.to_string()));
+        }
+    }
+
+    pub fn clear_cache(&self) {
+        if let Some(Ok(mut cache)) = self.cache.as_ref().map(|cache| cache.lock()) {
+            cache.clear();
+        }
+    }
+
+    // Only a missing file maps to None. Any other failure is still an error.
+    pub fn read_optional(&self, path: &str, localized: bool) -> Result<Option<Vec<u8>>> {
+        match self.read(path, localized) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(LayeredFilesystemError::FileNotFound(_, _)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    // Copies the file as stored on disk, so compressed files are never re-encoded.
+    pub fn copy(&self, src: &str, dst: &str, localized: bool) -> Result<()> {
+        self.check_writeable()?;
+        let (src_path, dst_path) = if localized {
+            (
+                self.path_localizer.localize(src, &self.language)?,
+                self.path_localizer.localize(dst, &self.language)?,
+            )
+        } else {
+            (src.to_string(), dst.to_string())
+        };
+        let bytes = self.read_raw(src_path)?;
+        self.forget_cached(self.write_layer(), &dst_path);
+        self.write_layer()
+            .write(&dst_path, &bytes)
+            .map_err(|err| LayeredFilesystemError::WriteError(dst_path, err.to_string()))
+    }
+
+    pub fn read_and_hash(&self, path: &str, localized: bool) -> Result<(Vec<u8>, u32)> {
+        let bytes = self.read(path, localized)?;
+        let hash = crc32fast::hash(&bytes);
+        Ok((bytes, hash))
+    }
+
     pub fn exists(&self, path: &str, localized: bool) -> Result<bool> {
         let actual_path = if localized {
             self.path_localizer.localize(path, &self.language)?
@@ -327,6 +555,7 @@ impl LayeredFilesystem {
     }
 
     pub fn create_dir(&self, path: &str, localized: bool) -> Result<()> {
+        self.check_writeable()?;
         let actual_path = if localized {
             self.path_localizer.localize(path, &self.language)?
         } else {
@@ -341,7 +570,7 @@ impl LayeredFilesystem {
         } else {
             path.to_string()
         };
-        for layer in self.layers.iter().rev() {
+        for layer in self.read_order() {
             if let Some(full_path) = layer.resolve(&actual_path) {
                 return Some(full_path);
             }
@@ -361,12 +590,36 @@ impl LayeredFilesystem {
         Ok(arc)
     }
 
+    // Entries are named relative to dir and stored exactly as they are on disk.
+    pub fn pack_arc(&self, dir: &str, localized: bool) -> Result<Vec<u8>> {
+        let actual_dir = if localized {
+            self.path_localizer.localize(dir, &self.language)?
+        } else {
+            dir.to_string()
+        };
+        let mut files: IndexMap<String, Vec<u8>> = IndexMap::new();
+        for path in self.list_files(&actual_dir, None, false)? {
+            let name = Path::new(&path)
+                .strip_prefix(&actual_dir)
+                .map(|name| name.to_string_lossy().replace('\\', "/"))
+                .unwrap_or_else(|_| path.clone());
+            files.insert(name, self.read_raw(path)?);
+        }
+        Ok(arc::to_bytes(&files)?)
+    }
+
     pub fn read_archive(&self, path: &str, localized: bool) -> Result<BinArchive> {
         let bytes = self.read(path, localized)?;
         let archive = BinArchive::from_bytes(&bytes, self.endian)?;
         Ok(archive)
     }
 
+    // Reads an archive and parses it into a typed model in one step.
+    pub fn read_as<T: FromArchive>(&self, path: &str, localized: bool) -> Result<T> {
+        let archive = self.read_archive(path, localized)?;
+        Ok(T::from_archive(&archive)?)
+    }
+
     pub fn read_text_archive(&self, path: &str, localized: bool) -> Result<TextArchive> {
         let bytes = self.read(path, localized)?;
         let archive = TextArchive::from_bytes(&bytes, self.text_archive_format, self.endian)?;
@@ -405,28 +658,80 @@ impl LayeredFilesystem {
         Ok(texture_vec_to_map(cgfx::read(&bytes)?))
     }
 
+    // Searches every texture container under dir (in sorted path order) for a texture
+    // with the given filename. Containers are identified by extension, ignoring compression.
+    pub fn find_texture(&self, dir: &str, name: &str, localized: bool) -> Result<Option<Texture>> {
+        for path in self.list_files(dir, None, localized)? {
+            let container_path = if self.would_compress(&path) {
+                Path::new(&path).with_extension("")
+            } else {
+                PathBuf::from(&path)
+            };
+            let extension = container_path
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase());
+            let parsed = match extension.as_deref() {
+                Some("bch") => bch::read(&self.read(&path, false)?),
+                Some("ctpk") => ctpk::read(&self.read(&path, false)?),
+                Some("cgfx") => cgfx::read(&self.read(&path, false)?),
+                Some("tpl") => Tpl::extract_textures(&self.read(&path, false)?),
+                _ => continue,
+            };
+            // One unreadable container shouldn't hide matches in the rest.
+            let textures = match parsed {
+                Ok(textures) => textures,
+                Err(_) => continue,
+            };
+            if let Some(texture) = textures.into_iter().find(|t| t.filename == name) {
+                return Ok(Some(texture));
+            }
+        }
+        Ok(None)
+    }
+
     pub fn write(&self, path: &str, bytes: &[u8], localized: bool) -> Result<()> {
+        self.write_with_compression(path, bytes, None, localized)
+    }
+
+    pub fn write_with_compression(
+        &self,
+        path: &str,
+        bytes: &[u8],
+        format: Option<CompressionFormat>,
+        localized: bool,
+    ) -> Result<()> {
+        self.check_writeable()?;
         let actual_path = if localized {
             self.path_localizer.localize(path, &self.language)?
         } else {
             path.to_string()
         };
 
-        let contents = if self.compression_format.is_compressed_filename(path) {
-            Cow::Owned(self.compression_format.compress(bytes)?)
-        } else {
-            Cow::Borrowed(bytes)
+        let contents = match format {
+            Some(format) => Cow::Owned(format.compress(bytes)?),
+            None => {
+                if self.would_compress(&actual_path) {
+                    Cow::Owned(self.compression_format.compress(bytes)?)
+                } else {
+                    Cow::Borrowed(bytes)
+                }
+            }
         };
 
         let layer = self
             .layers
             .last()
             .ok_or(LayeredFilesystemError::NoWriteableLayers)?;
+        self.forget_cached(layer, &actual_path);
         layer
             .write(&actual_path, &contents)
             .map_err(|err| LayeredFilesystemError::WriteError(actual_path, err.to_string()))
     }
 
+    pub fn would_compress(&self, path: &str) -> bool {
+        self.compression_format.is_compressed_filename(path)
+    }
+
     pub fn write_archive(&self, path: &str, archive: &BinArchive, localized: bool) -> Result<()> {
         let bytes = archive.serialize()?;
         self.write(path, &bytes, localized)
@@ -458,6 +763,31 @@ impl LayeredFilesystem {
         self.text_archive_format
     }
 
+    // Ordered from the bottom layer to the top (write) layer.
+    pub fn layer_roots(&self) -> Vec<&str> {
+        self.layers.iter().map(|layer| layer.root()).collect()
+    }
+
+    pub fn read_policy(&self) -> ReadPolicy {
+        self.read_policy
+    }
+
+    pub fn set_read_policy(&mut self, read_policy: ReadPolicy) {
+        self.read_policy = read_policy;
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn check_writeable(&self) -> Result<()> {
+        if self.read_only {
+            Err(LayeredFilesystemError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn write_layer(&self) -> &FileSystemLayer {
         &self.layers[self.layers.len() - 1]
     }
@@ -466,6 +796,8 @@ impl LayeredFilesystem {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::utils::load_test_file;
+    use crate::{FE14ASet, TextureWriteOptions};
     use std::path::PathBuf;
 
     #[test]
@@ -512,6 +844,24 @@ mod test {
         let text = fs.list("Subdir/", Some("**/*.txt"), false).unwrap();
         assert_eq!(4, all_files.len());
         assert_eq!(2, text.len());
+
+        // The root holds only Subdir, which holds the four files.
+        let deep = fs.list_with_depth("", None, None, false).unwrap();
+        let shallow = fs.list_with_depth("", None, Some(1), false).unwrap();
+        assert_eq!(5, deep.len());
+        assert_eq!(vec!["Subdir"], shallow);
+        assert_eq!(5, fs.list("", None, false).unwrap().len());
+        assert_eq!(
+            4,
+            fs.list_with_depth("Subdir/", None, Some(1), false)
+                .unwrap()
+                .len()
+        );
+
+        for path in &all_files {
+            assert!(path.starts_with("Subdir/"));
+            assert!(fs.file_exists(path, false).unwrap());
+        }
     }
 
     #[test]
@@ -558,4 +908,347 @@ mod test {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), "MyString".as_bytes());
     }
+
+    #[test]
+    fn read_and_hash() {
+        let layer = tempfile::tempdir().unwrap();
+        let layer_path = layer.path().to_string_lossy().to_string();
+        let fs = LayeredFilesystem::new(vec![layer_path], Language::EnglishNA, Game::FE14).unwrap();
+        fs.write("Test.bin.lz", b"123456789", false).unwrap();
+
+        // Hash is computed over the decompressed contents.
+        let (bytes, hash) = fs.read_and_hash("Test.bin.lz", false).unwrap();
+        assert_eq!(b"123456789".to_vec(), bytes);
+        assert_eq!(0xCBF43926, hash);
+    }
+
+    #[test]
+    fn copy_preserves_raw_bytes() {
+        let layer1 = tempfile::tempdir().unwrap();
+        let layer2 = tempfile::tempdir().unwrap();
+        let layer1_path = layer1.path().to_string_lossy().to_string();
+        let layer2_path = layer2.path().to_string_lossy().to_string();
+        let raw = LZ13CompressionFormat {}.compress(b"Original").unwrap();
+        std::fs::create_dir_all(layer1.path().join("m/@E")).unwrap();
+        std::fs::write(layer1.path().join("m/@E/GameData.bin.lz"), &raw).unwrap();
+        let fs = LayeredFilesystem::new(
+            vec![layer1_path, layer2_path],
+            Language::EnglishNA,
+            Game::FE14,
+        )
+        .unwrap();
+
+        fs.copy("m/GameData.bin.lz", "m/Copy.bin.lz", true).unwrap();
+        let copied = std::fs::read(layer2.path().join("m/@E/Copy.bin.lz")).unwrap();
+        assert_eq!(raw, copied);
+        assert_eq!(
+            b"Original".to_vec(),
+            fs.read("m/Copy.bin.lz", true).unwrap()
+        );
+        assert!(fs.copy("m/Missing.bin.lz", "m/Copy.bin.lz", true).is_err());
+    }
+
+    #[test]
+    fn read_optional() {
+        let layer = tempfile::tempdir().unwrap();
+        std::fs::write(layer.path().join("Test.bin"), b"Test").unwrap();
+        std::fs::write(layer.path().join("Bad.bin.lz"), b"Not compressed").unwrap();
+        let fs = LayeredFilesystem::new(
+            vec![layer.path().to_string_lossy().to_string()],
+            Language::EnglishNA,
+            Game::FE14,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some(b"Test".to_vec()),
+            fs.read_optional("Test.bin", false).unwrap()
+        );
+        assert_eq!(None, fs.read_optional("Missing.bin", false).unwrap());
+        assert!(fs.read_optional("Bad.bin.lz", false).is_err());
+    }
+
+    #[test]
+    fn compressed_extensions() {
+        let layer = tempfile::tempdir().unwrap();
+        let fs = LayeredFilesystem::new(
+            vec![layer.path().to_string_lossy().to_string()],
+            Language::EnglishNA,
+            Game::FE14,
+        )
+        .unwrap();
+
+        fs.write("Test.bin.lz", b"Plain", false).unwrap();
+        let raw = std::fs::read(layer.path().join("Test.bin.lz")).unwrap();
+        assert_eq!(LZ13CompressionFormat {}.compress(b"Plain").unwrap(), raw);
+        assert_eq!(b"Plain".to_vec(), fs.read("Test.bin.lz", false).unwrap());
+
+        fs.write("m/Test.bin.lz", b"Localized", true).unwrap();
+        let raw = std::fs::read(layer.path().join("m/@E/Test.bin.lz")).unwrap();
+        assert_eq!(
+            LZ13CompressionFormat {}.compress(b"Localized").unwrap(),
+            raw
+        );
+        assert_eq!(
+            b"Localized".to_vec(),
+            fs.read("m/Test.bin.lz", true).unwrap()
+        );
+
+        fs.write("foo.lz.bak", b"Backup", false).unwrap();
+        assert!(!fs.would_compress("foo.lz.bak"));
+        let raw = std::fs::read(layer.path().join("foo.lz.bak")).unwrap();
+        assert_eq!(b"Backup".to_vec(), raw);
+        assert_eq!(b"Backup".to_vec(), fs.read("foo.lz.bak", false).unwrap());
+    }
+
+    #[test]
+    fn list_files() {
+        let layer1 = tempfile::tempdir().unwrap();
+        let layer2 = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(layer1.path().join("dir/sub")).unwrap();
+        std::fs::create_dir_all(layer2.path().join("dir/other")).unwrap();
+        std::fs::write(layer1.path().join("dir/sub/a.bin"), b"A").unwrap();
+        std::fs::write(layer2.path().join("dir/b.bin"), b"B").unwrap();
+        let fs = LayeredFilesystem::new_raw(vec![
+            layer1.path().to_string_lossy().to_string(),
+            layer2.path().to_string_lossy().to_string(),
+        ])
+        .unwrap();
+
+        let files = fs.list_files("dir", None, false).unwrap();
+        assert_eq!(2, files.len());
+        assert!(files.iter().any(|f| f.ends_with("a.bin")));
+        assert!(files.iter().any(|f| f.ends_with("b.bin")));
+        assert!(fs.list("dir", None, false).unwrap().len() > files.len());
+    }
+
+    #[test]
+    fn pack_arc() {
+        let layer1 = tempfile::tempdir().unwrap();
+        let layer2 = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(layer1.path().join("mod/sub")).unwrap();
+        std::fs::create_dir_all(layer2.path().join("mod")).unwrap();
+        std::fs::write(layer1.path().join("mod/a.bin"), b"Original").unwrap();
+        std::fs::write(layer1.path().join("mod/sub/b.bin"), b"Nested").unwrap();
+        std::fs::write(layer2.path().join("mod/a.bin"), b"Shadowed").unwrap();
+        let fs = LayeredFilesystem::new_raw(vec![
+            layer1.path().to_string_lossy().to_string(),
+            layer2.path().to_string_lossy().to_string(),
+        ])
+        .unwrap();
+
+        let files = arc::from_bytes(&fs.pack_arc("mod", false).unwrap()).unwrap();
+        assert_eq!(2, files.len());
+        assert_eq!(b"Shadowed".to_vec(), files["a.bin"]);
+        assert_eq!(b"Nested".to_vec(), files["sub/b.bin"]);
+    }
+
+    #[test]
+    fn layer_roots() {
+        let layer1 = tempfile::tempdir().unwrap();
+        let layer2 = tempfile::tempdir().unwrap();
+        let fs = LayeredFilesystem::new(
+            vec![
+                layer1.path().to_string_lossy().to_string(),
+                layer2.path().to_string_lossy().to_string(),
+            ],
+            Language::EnglishNA,
+            Game::FE14,
+        )
+        .unwrap();
+        let roots = fs.layer_roots();
+        assert_eq!(2, roots.len());
+        assert_eq!(
+            layer1.path().normalize().unwrap().as_path(),
+            Path::new(roots[0])
+        );
+        assert_eq!(
+            layer2.path().normalize().unwrap().as_path(),
+            Path::new(roots[1])
+        );
+    }
+
+    #[test]
+    fn new_raw() {
+        let layer1 = tempfile::tempdir().unwrap();
+        let layer2 = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(layer1.path().join("m")).unwrap();
+        std::fs::write(layer1.path().join("m/GameData.bin.lz"), b"Original").unwrap();
+        let fs = LayeredFilesystem::new_raw(vec![
+            layer1.path().to_string_lossy().to_string(),
+            layer2.path().to_string_lossy().to_string(),
+        ])
+        .unwrap();
+
+        // Nothing is localized or decompressed.
+        assert!(!fs.would_compress("m/GameData.bin.lz"));
+        assert_eq!(
+            b"Original".to_vec(),
+            fs.read("m/GameData.bin.lz", true).unwrap()
+        );
+        fs.write("m/GameData.bin.lz", b"Updated", true).unwrap();
+        let raw = std::fs::read(layer2.path().join("m/GameData.bin.lz")).unwrap();
+        assert_eq!(b"Updated".to_vec(), raw);
+        assert!(LayeredFilesystem::new_raw(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn would_compress() {
+        let layer = tempfile::tempdir().unwrap();
+        let layer_path = layer.path().to_string_lossy().to_string();
+        let fs = LayeredFilesystem::new(vec![layer_path], Language::EnglishNA, Game::FE14).unwrap();
+        assert!(fs.would_compress("m/GameData.bin.lz"));
+        assert!(!fs.would_compress("m/GameData.bin"));
+    }
+
+    #[test]
+    fn write_with_compression_override() {
+        let layer = tempfile::tempdir().unwrap();
+        let layer_path = layer.path().to_string_lossy().to_string();
+        let fs = LayeredFilesystem::new(vec![layer_path], Language::EnglishNA, Game::FE14).unwrap();
+        let contents = load_test_file("LZ10Test.bin");
+
+        let lz10 = CompressionFormat::LZ10(LZ10CompressionFormat {});
+        fs.write_with_compression("Test.bin", &contents, Some(lz10.clone()), false)
+            .unwrap();
+        let raw = std::fs::read(layer.path().join("Test.bin")).unwrap();
+        assert_eq!(0x10, raw[0]);
+        assert_eq!(contents, lz10.decompress(&raw).unwrap());
+
+        fs.write_with_compression("Test.bin", &contents, None, false)
+            .unwrap();
+        let raw = std::fs::read(layer.path().join("Test.bin")).unwrap();
+        assert_eq!(contents, raw);
+    }
+
+    #[test]
+    fn read_as() {
+        let layer = tempfile::tempdir().unwrap();
+        let layer_path = layer.path().to_string_lossy().to_string();
+        let fs = LayeredFilesystem::new(vec![layer_path], Language::EnglishNA, Game::FE14).unwrap();
+        let bytes = load_test_file("FE14Aset_Test.bin");
+        fs.write("Test.bin", &bytes, false).unwrap();
+        let aset = fs.read_as::<FE14ASet>("Test.bin", false).unwrap();
+        assert_eq!(bytes, aset.serialize().unwrap());
+        assert!(fs.read_as::<FE14ASet>("Missing.bin", false).is_err());
+    }
+
+    #[test]
+    fn read_only() {
+        let layer = tempfile::tempdir().unwrap();
+        let layer_path = layer.path().to_string_lossy().to_string();
+        std::fs::write(layer.path().join("Test.bin"), [1, 2, 3]).unwrap();
+        let fs =
+            LayeredFilesystem::new_read_only(vec![layer_path], Language::EnglishNA, Game::FE14)
+                .unwrap();
+        assert!(fs.is_read_only());
+        assert_eq!(vec![1, 2, 3], fs.read("Test.bin", false).unwrap());
+        assert!(matches!(
+            fs.write("Other.bin", &[4], false),
+            Err(LayeredFilesystemError::ReadOnly)
+        ));
+        assert!(matches!(
+            fs.create_dir("Subdir", false),
+            Err(LayeredFilesystemError::ReadOnly)
+        ));
+        assert!(matches!(
+            fs.copy("Test.bin", "Copy.bin", false),
+            Err(LayeredFilesystemError::ReadOnly)
+        ));
+        assert!(!layer.path().join("Other.bin").exists());
+        assert!(!layer.path().join("Subdir").exists());
+    }
+
+    #[test]
+    fn find_texture() {
+        let layer = tempfile::tempdir().unwrap();
+        let layer_path = layer.path().to_string_lossy().to_string();
+        let fs = LayeredFilesystem::new(vec![layer_path], Language::EnglishNA, Game::FE14).unwrap();
+        let textures = vec![
+            Texture {
+                filename: "first".to_string(),
+                width: 8,
+                height: 8,
+                pixel_data: vec![1; 8 * 8 * 4],
+                ..Default::default()
+            },
+            Texture {
+                filename: "second".to_string(),
+                width: 8,
+                height: 8,
+                pixel_data: vec![2; 8 * 8 * 4],
+                ..Default::default()
+            },
+        ];
+        let bytes = ctpk::write(&textures, &TextureWriteOptions::default()).unwrap();
+        fs.create_dir("ui/nested", false).unwrap();
+        fs.write("ui/nested/Textures.ctpk.lz", &bytes, false)
+            .unwrap();
+        fs.write("ui/Other.bin", b"Not a texture", false).unwrap();
+        fs.write("ui/Broken.ctpk", b"Not a texture", false).unwrap();
+
+        let texture = fs.find_texture("ui", "second", false).unwrap().unwrap();
+        assert_eq!(vec![2; 8 * 8 * 4], texture.pixel_data);
+        assert!(fs.find_texture("ui", "missing", false).unwrap().is_none());
+    }
+
+    #[test]
+    fn read_policy() {
+        let layer1 = tempfile::tempdir().unwrap();
+        let layer2 = tempfile::tempdir().unwrap();
+        std::fs::write(layer1.path().join("Test.bin"), b"Bottom").unwrap();
+        std::fs::write(layer2.path().join("Test.bin"), b"Top").unwrap();
+        let mut fs = LayeredFilesystem::new_raw(vec![
+            layer1.path().to_string_lossy().to_string(),
+            layer2.path().to_string_lossy().to_string(),
+        ])
+        .unwrap();
+        assert_eq!(ReadPolicy::TopWins, fs.read_policy());
+        assert_eq!(b"Top".to_vec(), fs.read("Test.bin", false).unwrap());
+
+        fs.set_read_policy(ReadPolicy::BottomWins);
+        assert_eq!(b"Bottom".to_vec(), fs.read("Test.bin", false).unwrap());
+        let resolved = fs.resolve("Test.bin", false).unwrap();
+        assert!(resolved.starts_with(layer1.path()));
+        fs.write("Test.bin", b"Written", false).unwrap();
+        let written = std::fs::read(layer2.path().join("Test.bin")).unwrap();
+        assert_eq!(b"Written".to_vec(), written);
+    }
+
+    #[test]
+    fn read_cache() {
+        let layer = tempfile::tempdir().unwrap();
+        let layer_path = layer.path().to_string_lossy().to_string();
+        let mut fs =
+            LayeredFilesystem::new(vec![layer_path], Language::EnglishNA, Game::FE14).unwrap();
+        fs.set_cache_enabled(true);
+        fs.write("Test.bin.lz", b"Original", false).unwrap();
+        assert_eq!(b"Original".to_vec(), fs.read("Test.bin.lz", false).unwrap());
+
+        // Swap the contents behind the filesystem's back but keep the old mtime.
+        let path = layer.path().join("Test.bin.lz");
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, b"Not compressed").unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(modified).unwrap();
+        assert_eq!(b"Original".to_vec(), fs.read("Test.bin.lz", false).unwrap());
+
+        // A new mtime invalidates the entry.
+        file.set_modified(modified + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert!(fs.read("Test.bin.lz", false).is_err());
+
+        // Writes through the filesystem are never served stale.
+        fs.write("Test.bin.lz", b"Rewritten", false).unwrap();
+        assert_eq!(
+            b"Rewritten".to_vec(),
+            fs.read("Test.bin.lz", false).unwrap()
+        );
+        fs.clear_cache();
+        fs.set_cache_enabled(false);
+        assert_eq!(
+            b"Rewritten".to_vec(),
+            fs.read("Test.bin.lz", false).unwrap()
+        );
+    }
 }
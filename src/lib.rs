@@ -1,21 +1,26 @@
 mod asset_binary;
 mod bin_archive;
 mod bin_streams;
+mod cmpr;
 mod compression_format;
 mod encoded_strings;
 mod endian_aware_io;
 mod errors;
 mod etc1;
+mod from_archive;
 mod game;
 mod language;
 mod layered_filesystem;
 mod localization;
 mod lz10;
 mod lz13;
+mod padding;
 mod pixel_encodings;
 mod text_archive;
 mod texture;
+mod texture_container;
 mod texture_decoder;
+mod texture_encoder;
 mod texture_utils;
 
 pub mod arc;
@@ -31,22 +36,29 @@ mod utils;
 
 use endian_aware_io::{EndianAwareReader, EndianAwareWriter};
 
-pub use asset_binary::{AssetBinary, AssetSpec};
-pub use bin_archive::BinArchive;
+pub use asset_binary::{AssetBinary, AssetFieldValue, AssetSpec};
+pub use bin_archive::{ArchiveDump, ArchiveIndex, ArchiveWarning, BinArchive, RemovedMetadata};
 pub use bin_streams::{BinArchiveReader, BinArchiveWriter};
-pub use compression_format::CompressionFormat;
+pub use compression_format::{CompressionFormat, NoOpCompressionFormat};
 pub use encoded_strings::EncodedStringReader;
 pub use endian_aware_io::Endian;
-pub use etc1::decode;
+pub use etc1::{decode, decode_block};
 pub use fe14_aset::FE14ASet;
+pub use from_archive::FromArchive;
 pub use game::Game;
 pub use language::Language;
-pub use layered_filesystem::LayeredFilesystem;
+pub use layered_filesystem::{LayeredFilesystem, ReadPolicy};
 pub use lz10::LZ10CompressionFormat;
 pub use lz13::LZ13CompressionFormat;
 pub use pixel_encodings::ColorFormat;
-pub use text_archive::{TextArchive, TextArchiveFormat};
-pub use texture::Texture;
+pub use text_archive::{MessageDiff, TextArchive, TextArchiveFormat};
+pub use texture::{
+    atlas_positions, pack_atlas, supported_read_formats, supported_write_formats, Texture,
+    TextureSourceFormat,
+};
+pub use texture_container::{TextureContainer, TextureWriteOptions};
+pub use texture_decoder::{pixel_format_name, HardwareTextureFormat};
+pub use texture_utils::TextureLengthMismatch;
 
 pub use errors::{
     ArcError, ArchiveError, CompressionError, DialogueError, EncodedStringsError,
@@ -62,6 +62,14 @@ impl LZ10CompressionFormat {
         Ok(buf)
     }
 
+    // Reads the 24 bit length from the header without decompressing anything.
+    pub fn decompressed_size(&self, bytes: &[u8]) -> Result<usize> {
+        if bytes.len() < 4 || bytes[0] != 0x10 {
+            return Err(CompressionError::InvalidInput("LZ10".to_string()));
+        }
+        Ok(bytes[1] as usize | (bytes[2] as usize) << 8 | (bytes[3] as usize) << 16)
+    }
+
     pub fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>> {
         match nintendo_lz::decompress_arr(bytes) {
             Ok(decompressed_data) => Ok(decompressed_data),
@@ -86,4 +94,14 @@ mod test {
         assert!(actual_decompressed.is_ok());
         assert_eq!(decompressed, actual_decompressed.unwrap());
     }
+
+    #[test]
+    fn lz10_decompressed_size() {
+        let decompressed = load_test_file("LZ10Test.bin");
+        let lz10 = LZ10CompressionFormat {};
+        let compressed = lz10.compress(&decompressed).unwrap();
+        let size = lz10.decompressed_size(&compressed).unwrap();
+        assert_eq!(decompressed.len(), size);
+        assert!(lz10.decompressed_size(&compressed[..3]).is_err());
+    }
 }
@@ -111,6 +111,18 @@ impl LZ13CompressionFormat {
         Ok(result)
     }
 
+    // Stored form: a 0x00 header with the 24 bit length, followed by the bytes as-is.
+    pub fn store(&self, bytes: &[u8]) -> Vec<u8> {
+        let length = bytes.len();
+        let mut result: Vec<u8> = Vec::with_capacity(4 + length);
+        result.push(0);
+        result.push((length & 0xFF) as u8);
+        result.push(((length >> 8) & 0xFF) as u8);
+        result.push(((length >> 16) & 0xFF) as u8);
+        result.extend_from_slice(bytes);
+        result
+    }
+
     pub fn decompress(&self, bytes: &[u8]) -> Result<Vec<u8>> {
         if bytes[0] == 0 {
             let mut result: Vec<u8> = Vec::new();
@@ -151,4 +163,18 @@ mod test {
         assert!(actual_compressed.is_ok());
         assert_eq!(actual_compressed.unwrap(), compressed);
     }
+
+    #[test]
+    fn lz13_store_round_trip() {
+        let decompressed = load_test_file("LZ13Test.bin");
+        let lz13 = LZ13CompressionFormat {};
+        let stored = lz13.store(&decompressed);
+        let length = decompressed.len();
+        assert_eq!(
+            vec![0, length as u8, (length >> 8) as u8, (length >> 16) as u8],
+            stored[..4]
+        );
+        assert_eq!(decompressed, stored[4..]);
+        assert_eq!(decompressed, lz13.decompress(&stored).unwrap());
+    }
 }
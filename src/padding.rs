@@ -0,0 +1,62 @@
+// Rounds the value up to the next multiple of the alignment.
+pub fn align(value: usize, alignment: usize) -> usize {
+    if alignment <= 1 {
+        value
+    } else {
+        value.next_multiple_of(alignment)
+    }
+}
+
+// Same as align, but None if rounding up would overflow.
+pub fn checked_align(value: usize, alignment: usize) -> Option<usize> {
+    if alignment <= 1 {
+        Some(value)
+    } else {
+        value.checked_next_multiple_of(alignment)
+    }
+}
+
+// Pads the buffer with zeroes until its length is a multiple of the alignment.
+pub fn pad_to(buffer: &mut Vec<u8>, alignment: usize) {
+    pad_to_with_offset(buffer, 0, alignment);
+}
+
+// Same as pad_to, but for buffers that will be written at a non-zero offset.
+// Pads until offset + buffer length is a multiple of the alignment.
+pub fn pad_to_with_offset(buffer: &mut Vec<u8>, offset: usize, alignment: usize) {
+    buffer.resize(align(offset + buffer.len(), alignment) - offset, 0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn align_rounds_up() {
+        assert_eq!(0, align(0, 4));
+        assert_eq!(8, align(5, 4));
+        assert_eq!(8, align(8, 4));
+        assert_eq!(5, align(5, 0));
+        assert_eq!(Some(8), checked_align(5, 4));
+        assert_eq!(None, checked_align(usize::MAX - 1, 4));
+    }
+
+    #[test]
+    fn pad_to_aligns_length() {
+        let mut buffer = vec![1, 2, 3, 4, 5];
+        pad_to(&mut buffer, 4);
+        assert_eq!(vec![1, 2, 3, 4, 5, 0, 0, 0], buffer);
+        pad_to(&mut buffer, 4);
+        assert_eq!(8, buffer.len());
+    }
+
+    #[test]
+    fn pad_to_with_offset_aligns_end() {
+        let mut buffer = vec![1];
+        pad_to_with_offset(&mut buffer, 8, 32);
+        assert_eq!(24, buffer.len());
+        let mut empty = Vec::new();
+        pad_to_with_offset(&mut empty, 32, 32);
+        assert!(empty.is_empty());
+    }
+}
@@ -6,6 +6,8 @@ type Result<T> = std::result::Result<T, TextureDecodeError>;
 pub enum ColorFormat {
     RGBA8,
     RGB5A3,
+    IA8,
+    A8,
     CI8,
     Unrecognized,
 }
@@ -26,6 +28,30 @@ pub fn decode_rgb5a3_pixel(value: u16) -> Vec<u8> {
     }
 }
 
+// Alpha is stored in the high byte, intensity in the low byte.
+pub fn decode_ia8_pixel(value: u16) -> Vec<u8> {
+    let a = (value >> 8) as u8;
+    let i = (value & 0xFF) as u8;
+    vec![i, i, i, a]
+}
+
+// Opaque colors use the 5 bit RGB form, anything else falls back to 4 bit RGB with 3 bit alpha.
+pub fn encode_rgb5a3_pixel(rgba: &[u8]) -> u16 {
+    let channel = |i: usize| rgba[i] as u16;
+    let (r, g, b, a) = (channel(0), channel(1), channel(2), channel(3));
+    if a == 0xFF {
+        0x8000 | ((r >> 3) << 10) | ((g >> 3) << 5) | (b >> 3)
+    } else {
+        ((a >> 5) << 12) | ((r >> 4) << 8) | ((g >> 4) << 4) | (b >> 4)
+    }
+}
+
+// Intensity is the average of the color channels.
+pub fn encode_ia8_pixel(rgba: &[u8]) -> u16 {
+    let i = (rgba[0] as u16 + rgba[1] as u16 + rgba[2] as u16) / 3;
+    ((rgba[3] as u16) << 8) | i
+}
+
 // TODO: Current logic assumes we have integral bytes per pixel, not always the case.
 impl ColorFormat {
     pub fn decode(&self, pixel_data: &[u8]) -> Result<Vec<u8>> {
@@ -51,6 +77,13 @@ impl ColorFormat {
                     let value = Endian::Big.decode_u16(&pixel_data[i..i + 2])?;
                     decoded.extend(decode_rgb5a3_pixel(value));
                 }
+                ColorFormat::IA8 => {
+                    let value = Endian::Big.decode_u16(&pixel_data[i..i + 2])?;
+                    decoded.extend(decode_ia8_pixel(value));
+                }
+                ColorFormat::A8 => {
+                    decoded.extend_from_slice(&[0xFF, 0xFF, 0xFF, pixel_data[i]]);
+                }
                 _ => {}
             }
         }
@@ -86,10 +119,71 @@ impl ColorFormat {
         Ok(decoded)
     }
 
+    pub fn encode(&self, rgba_data: &[u8]) -> Result<Vec<u8>> {
+        if let ColorFormat::Unrecognized = self {
+            return Err(TextureDecodeError::UnsupportedFormat);
+        }
+        if self.is_indexed_format() {
+            return Err(TextureDecodeError::NoPalette);
+        }
+        if !rgba_data.len().is_multiple_of(4) {
+            return Err(TextureDecodeError::UnalignedData);
+        }
+
+        let mut encoded: Vec<u8> = Vec::new();
+        for color in rgba_data.chunks(4) {
+            match self {
+                ColorFormat::RGBA8 => encoded.extend_from_slice(color),
+                ColorFormat::RGB5A3 => {
+                    encoded.extend(Endian::Big.encode_u16(encode_rgb5a3_pixel(color)))
+                }
+                ColorFormat::IA8 => encoded.extend(Endian::Big.encode_u16(encode_ia8_pixel(color))),
+                ColorFormat::A8 => encoded.push(color[3]),
+                _ => {}
+            }
+        }
+        Ok(encoded)
+    }
+
+    // Maps each color to the index of the closest palette entry.
+    pub fn encode_indexed(&self, rgba_data: &[u8], rgba_palette: &[u8]) -> Result<Vec<u8>> {
+        if let ColorFormat::Unrecognized = self {
+            return Err(TextureDecodeError::UnsupportedFormat);
+        }
+        if !self.is_indexed_format() {
+            return Err(TextureDecodeError::NotIndexed);
+        }
+        if !rgba_data.len().is_multiple_of(4) || !rgba_palette.len().is_multiple_of(4) {
+            return Err(TextureDecodeError::UnalignedData);
+        }
+        if rgba_palette.is_empty() || rgba_palette.len() / 4 > 256 {
+            return Err(TextureDecodeError::OutOfBoundsIndex);
+        }
+
+        let distance = |a: &[u8], b: &[u8]| -> u32 {
+            a.iter()
+                .zip(b)
+                .map(|(x, y)| (*x as i32 - *y as i32).pow(2) as u32)
+                .sum()
+        };
+        let mut encoded: Vec<u8> = Vec::new();
+        for color in rgba_data.chunks(4) {
+            let index = rgba_palette
+                .chunks(4)
+                .enumerate()
+                .min_by_key(|(_, entry)| distance(color, entry))
+                .map_or(0, |(index, _)| index);
+            encoded.push(index as u8);
+        }
+        Ok(encoded)
+    }
+
     pub fn is_indexed_format(&self) -> bool {
         match self {
             ColorFormat::RGBA8 => false,
             ColorFormat::RGB5A3 => false,
+            ColorFormat::IA8 => false,
+            ColorFormat::A8 => false,
             ColorFormat::CI8 => true,
             ColorFormat::Unrecognized => false,
         }
@@ -99,8 +193,56 @@ impl ColorFormat {
         match self {
             ColorFormat::RGBA8 => 4,
             ColorFormat::RGB5A3 => 2,
+            ColorFormat::IA8 => 2,
+            ColorFormat::A8 => 1,
             ColorFormat::CI8 => 1,
             ColorFormat::Unrecognized => 0,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decode_ia8() {
+        let decoded = ColorFormat::IA8.decode(&[0x80, 0x40, 0xFF, 0x10]).unwrap();
+        assert_eq!(
+            vec![0x40, 0x40, 0x40, 0x80, 0x10, 0x10, 0x10, 0xFF],
+            decoded
+        );
+        assert!(ColorFormat::IA8.decode(&[0x80]).is_err());
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let colors = [0xF8, 0x08, 0x40, 0xFF, 0x22, 0x44, 0x66, 0x60];
+        for format in &[ColorFormat::RGBA8, ColorFormat::RGB5A3] {
+            let encoded = format.encode(&colors).unwrap();
+            assert_eq!(colors.to_vec(), format.decode(&encoded).unwrap());
+        }
+        let encoded = ColorFormat::IA8.encode(&[0x40, 0x40, 0x40, 0x80]).unwrap();
+        assert_eq!(vec![0x80, 0x40], encoded);
+    }
+
+    #[test]
+    fn encode_indexed() {
+        let palette = [0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        let colors = [0xF0, 0xF0, 0xF0, 0xFF, 0x10, 0, 0, 0xFF];
+        let encoded = ColorFormat::CI8.encode_indexed(&colors, &palette).unwrap();
+        assert_eq!(vec![1, 0], encoded);
+        let not_indexed = ColorFormat::RGB5A3.encode_indexed(&[0; 4], &palette);
+        assert!(not_indexed.is_err());
+        assert!(ColorFormat::CI8.encode(&[0; 4]).is_err());
+    }
+
+    #[test]
+    fn decode_a8() {
+        let decoded = ColorFormat::A8.decode(&[0x20, 0xFF]).unwrap();
+        assert_eq!(
+            vec![0xFF, 0xFF, 0xFF, 0x20, 0xFF, 0xFF, 0xFF, 0xFF],
+            decoded
+        );
+    }
+}
@@ -1,39 +1,73 @@
 use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Cursor;
 
-use crate::encoded_strings::{to_shift_jis, to_utf_16};
-use crate::{BinArchive, BinArchiveReader, EncodedStringReader, Endian, TextArchiveError};
+use crate::encoded_strings::{to_shift_jis, to_utf_16_endian};
+use crate::padding::{align, pad_to};
+use crate::{
+    ArchiveError, BinArchive, BinArchiveReader, EncodedStringReader, Endian, EndianAwareReader,
+    TextArchiveError,
+};
 
 type Result<T> = std::result::Result<T, TextArchiveError>;
 
-fn write_shift_jis_string(bytes: &mut Vec<u8>, string: &str) -> Result<()> {
+fn write_shift_jis_string(bytes: &mut Vec<u8>, string: &str, alignment: usize) -> Result<()> {
     bytes.extend(to_shift_jis(string)?);
     bytes.push(0);
-    while bytes.len() % 4 != 0 {
-        bytes.push(0);
-    }
+    pad_to(bytes, alignment);
     Ok(())
 }
 
-fn write_utf_16_string(bytes: &mut Vec<u8>, string: &str) -> Result<()> {
-    bytes.extend(to_utf_16(string)?);
+fn write_utf_16_string(
+    bytes: &mut Vec<u8>,
+    string: &str,
+    endian: Endian,
+    alignment: usize,
+) -> Result<()> {
+    bytes.extend(to_utf_16_endian(string, endian)?);
     bytes.push(0);
     bytes.push(0);
-    while bytes.len() % 4 != 0 {
-        bytes.push(0);
-    }
+    pad_to(bytes, alignment);
     Ok(())
 }
 
+// Converts a message from its editable form. Pasted CRLF/CR line endings become LF first.
+fn unescape_message(message: &str) -> String {
+    message
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+        .replace("\\n", "\n")
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum TextArchiveFormat {
     ShiftJIS,
     Unicode,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageDiff {
+    Added(String),
+    Removed(String),
+    Changed {
+        key: String,
+        old: String,
+        new: String,
+    },
+}
+
 pub struct TextArchive {
     title: String,
     entries: IndexMap<String, String>,
+    // FE10 delimits message groups with unlabeled strings and extra labels that share
+    // an address with a message. Neither fits the key -> message model, so they're
+    // tracked separately to survive a round trip.
+    unlabeled: Vec<(usize, String)>,
+    aliases: HashMap<String, Vec<String>>,
     dirty: bool,
+    dirty_keys: HashSet<String>,
+    // Messages (and the title) are padded so each one starts on this boundary.
+    alignment: usize,
     format: TextArchiveFormat,
     endian: Endian,
 }
@@ -43,12 +77,80 @@ impl TextArchive {
         TextArchive {
             title: "".to_string(),
             entries: IndexMap::new(),
+            unlabeled: Vec::new(),
+            aliases: HashMap::new(),
             dirty: false,
+            dirty_keys: HashSet::new(),
+            alignment: 4,
             format,
             endian,
         }
     }
 
+    // Messages are stored exactly as given, without the \\n escape handling set_message does.
+    // The result starts out clean. Later entries with a duplicate key replace earlier ones.
+    pub fn from_entries(
+        title: String,
+        entries: Vec<(String, String)>,
+        format: TextArchiveFormat,
+        endian: Endian,
+    ) -> Self {
+        let mut archive = TextArchive::new(format, endian);
+        archive.title = title;
+        archive.entries = entries.into_iter().collect();
+        archive
+    }
+
+    // A clean archive holding only the keys that start with prefix, in their original order.
+    // Aliases on those keys carry over. Unlabeled strings don't belong to a key, so they're dropped.
+    pub fn split_by_prefix(&self, prefix: &str) -> TextArchive {
+        let mut archive = TextArchive::new(self.format, self.endian);
+        archive.title = self.title.clone();
+        archive.alignment = self.alignment;
+        for (key, message) in &self.entries {
+            if !key.starts_with(prefix) {
+                continue;
+            }
+            archive.entries.insert(key.clone(), message.clone());
+            if let Some(aliases) = self.aliases.get(key) {
+                archive.aliases.insert(key.clone(), aliases.clone());
+            }
+        }
+        archive
+    }
+
+    fn push_message(&mut self, mut labels: Vec<String>, message: String) {
+        if labels.is_empty() {
+            self.unlabeled.push((self.entries.len(), message));
+        } else {
+            let key = labels.remove(0);
+            if !labels.is_empty() {
+                self.aliases.insert(key.clone(), labels);
+            }
+            self.entries.insert(key, message);
+        }
+    }
+
+    // Returns every message in serialization order along with the labels attached to it.
+    fn ordered_messages(&self) -> Vec<(Vec<&String>, &String)> {
+        let mut messages = Vec::new();
+        let mut unlabeled = self.unlabeled.iter().peekable();
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            while let Some((_, message)) = unlabeled.next_if(|(index, _)| *index <= i) {
+                messages.push((Vec::new(), message));
+            }
+            let mut labels = vec![key];
+            if let Some(aliases) = self.aliases.get(key) {
+                labels.extend(aliases);
+            }
+            messages.push((labels, value));
+        }
+        for (_, message) in unlabeled {
+            messages.push((Vec::new(), message));
+        }
+        messages
+    }
+
     pub fn get_entries(&self) -> &IndexMap<String, String> {
         &self.entries
     }
@@ -63,25 +165,108 @@ impl TextArchive {
         TextArchive::from_archive(&bin_archive, format, endian)
     }
 
+    // For archives written with an alignment above 4, so the extra padding isn't read as messages.
+    pub fn from_bytes_with_alignment(
+        raw_archive: &[u8],
+        format: TextArchiveFormat,
+        endian: Endian,
+        alignment: usize,
+    ) -> Result<Self> {
+        let bin_archive = BinArchive::from_bytes(raw_archive, endian)?;
+        TextArchive::from_archive_with_alignment(&bin_archive, format, endian, alignment)
+    }
+
+    pub fn from_legacy_bytes(raw_archive: &[u8], endian: Endian) -> Result<Self> {
+        if raw_archive.len() < 0x20 {
+            return Err(ArchiveError::ArchiveTooSmall.into());
+        }
+        let mut cursor = Cursor::new(raw_archive);
+        cursor.set_position(4);
+        let data_size = cursor.read_u32(endian).map_err(ArchiveError::from)? as usize;
+        let pointer_count = cursor.read_u32(endian).map_err(ArchiveError::from)? as usize;
+        let label_count = cursor.read_u32(endian).map_err(ArchiveError::from)? as usize;
+        let label_start = 0x20 + align(data_size, 4) + pointer_count * 4;
+        let text_start = label_start + label_count * 8;
+        if text_start > raw_archive.len() {
+            return Err(ArchiveError::ArchiveTooSmall.into());
+        }
+
+        let mut labels: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for i in 0..label_count {
+            cursor.set_position((label_start + i * 8) as u64);
+            let address = cursor.read_u32(endian).map_err(ArchiveError::from)? as usize;
+            let offset = cursor.read_u32(endian).map_err(ArchiveError::from)? as usize;
+            if address >= data_size {
+                return Err(ArchiveError::OutOfBoundsAddress(address, data_size).into());
+            }
+            cursor.set_position((text_start + offset) as u64);
+            let label = cursor.read_shift_jis_string()?;
+            labels.entry(address).or_default().push(label);
+        }
+
+        // Walk the strings the same way from_archive does so unlabeled messages are kept.
+        let mut cursor = Cursor::new(&raw_archive[0x20..0x20 + data_size]);
+        let mut text_archive = TextArchive::new(TextArchiveFormat::ShiftJIS, endian);
+        while (cursor.position() as usize) < data_size {
+            let address = cursor.position() as usize;
+            let message = cursor.read_shift_jis_string()?;
+            cursor.set_position(align(cursor.position() as usize, 4) as u64);
+            let labels = labels.remove(&address).unwrap_or_default();
+            text_archive.push_message(labels, message);
+        }
+        Ok(text_archive)
+    }
+
+    // Unicode archives open with an unlabeled Shift-JIS title and label every message after it.
+    // Shift-JIS messages read as UTF-16 tend to run into each other, which drops labels, so a
+    // Unicode parse that accounts for every label is trusted. Anything else that parses is Shift-JIS.
+    pub fn detect_format(bytes: &[u8], endian: Endian) -> Option<TextArchiveFormat> {
+        let archive = BinArchive::from_bytes(bytes, endian).ok()?;
+        let title_labeled = archive.read_labels(0).ok().flatten().is_some();
+        if !title_labeled {
+            let unicode = TextArchive::from_archive(&archive, TextArchiveFormat::Unicode, endian);
+            if let Ok(unicode) = unicode {
+                let alias_count: usize = unicode.aliases.values().map(Vec::len).sum();
+                let label_count = unicode.entries.len() + alias_count;
+                if unicode.unlabeled.is_empty() && label_count == archive.get_labels().len() {
+                    return Some(TextArchiveFormat::Unicode);
+                }
+            }
+        }
+        TextArchive::from_archive(&archive, TextArchiveFormat::ShiftJIS, endian)
+            .ok()
+            .map(|_| TextArchiveFormat::ShiftJIS)
+    }
+
     pub fn from_archive(
         archive: &BinArchive,
         format: TextArchiveFormat,
         endian: Endian,
+    ) -> Result<Self> {
+        TextArchive::from_archive_with_alignment(archive, format, endian, 4)
+    }
+
+    pub fn from_archive_with_alignment(
+        archive: &BinArchive,
+        format: TextArchiveFormat,
+        endian: Endian,
+        alignment: usize,
     ) -> Result<Self> {
         let mut reader = BinArchiveReader::new(archive, 0);
         let mut text_archive = TextArchive::new(format, endian);
+        text_archive.set_alignment(alignment);
         if let TextArchiveFormat::Unicode = format {
             text_archive.title = reader.read_shift_jis_string()?;
+            reader.seek(align(reader.tell(), text_archive.alignment));
         }
         while reader.tell() < archive.size() {
             let labels = reader.read_labels()?.unwrap_or_else(Vec::new);
             let message = match format {
                 TextArchiveFormat::ShiftJIS => reader.read_shift_jis_string()?,
-                TextArchiveFormat::Unicode => reader.read_utf_16_string()?,
+                TextArchiveFormat::Unicode => reader.read_utf_16_string_endian(endian)?,
             };
-            if let Some(k) = labels.first() {
-                text_archive.entries.insert(k.clone(), message);
-            }
+            reader.seek(align(reader.tell(), text_archive.alignment));
+            text_archive.push_message(labels, message);
         }
         Ok(text_archive)
     }
@@ -92,14 +277,13 @@ impl TextArchive {
 
         // Early versions of the format don't have a title.
         if let TextArchiveFormat::Unicode = self.format {
-            write_shift_jis_string(&mut bytes, &self.title)?;
+            write_shift_jis_string(&mut bytes, &self.title, self.alignment)?;
         }
-        for (key, value) in &self.entries {
-            label_info.push((key, bytes.len()));
-            match self.format {
-                TextArchiveFormat::ShiftJIS => write_shift_jis_string(&mut bytes, value)?,
-                TextArchiveFormat::Unicode => write_utf_16_string(&mut bytes, value)?,
+        for (labels, value) in self.ordered_messages() {
+            for label in labels {
+                label_info.push((label, bytes.len()));
             }
+            self.write_message(&mut bytes, value)?;
         }
 
         let mut archive = BinArchive::new(self.endian);
@@ -112,6 +296,84 @@ impl TextArchive {
         Ok(bytes)
     }
 
+    fn write_message(&self, bytes: &mut Vec<u8>, message: &str) -> Result<()> {
+        match self.format {
+            TextArchiveFormat::ShiftJIS => write_shift_jis_string(bytes, message, self.alignment),
+            TextArchiveFormat::Unicode => {
+                write_utf_16_string(bytes, message, self.endian, self.alignment)
+            }
+        }
+    }
+
+    // The message as serialize writes it, including the terminator and padding.
+    // Combined with offset_of, this locates the message in the data section.
+    pub fn message_bytes(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        match self.entries.get(key) {
+            Some(message) => {
+                let mut bytes = Vec::new();
+                self.write_message(&mut bytes, message)?;
+                Ok(Some(bytes))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Checks for problems that serialize would happily write out but the game can't use.
+    pub fn validate(&self) -> Result<()> {
+        let mut seen: HashSet<&str> = HashSet::new();
+        for (labels, value) in self.ordered_messages() {
+            for label in labels {
+                if label.is_empty() {
+                    return Err(TextArchiveError::MissingKey);
+                }
+                if !seen.insert(label) {
+                    return Err(TextArchiveError::OtherError(format!(
+                        "Duplicate key '{}'.",
+                        label
+                    )));
+                }
+            }
+            self.encoded_length(value)?;
+        }
+        Ok(())
+    }
+
+    fn encoded_length(&self, message: &str) -> Result<usize> {
+        let length = match self.format {
+            TextArchiveFormat::ShiftJIS => to_shift_jis(message)?.len() + 1,
+            TextArchiveFormat::Unicode => message.encode_utf16().count() * 2 + 2,
+        };
+        Ok(align(length, self.alignment))
+    }
+
+    // The offset is relative to the start of the archive's data section.
+    pub fn offset_of(&self, key: &str) -> Option<usize> {
+        if !self.entries.contains_key(key) {
+            return None;
+        }
+        let mut offset = match self.format {
+            TextArchiveFormat::ShiftJIS => 0,
+            TextArchiveFormat::Unicode => {
+                align(to_shift_jis(&self.title).ok()?.len() + 1, self.alignment)
+            }
+        };
+        for (labels, value) in self.ordered_messages() {
+            if labels.first().is_some_and(|label| *label == key) {
+                return Some(offset);
+            }
+            offset += self.encoded_length(value).ok()?;
+        }
+        None
+    }
+
+    pub fn alignment(&self) -> usize {
+        self.alignment
+    }
+
+    pub fn set_alignment(&mut self, alignment: usize) {
+        self.alignment = alignment.max(1);
+    }
+
     pub fn get_title(&self) -> &str {
         &self.title
     }
@@ -125,23 +387,103 @@ impl TextArchive {
     }
 
     pub fn delete_message(&mut self, key: &str) {
-        self.entries.shift_remove(key);
+        if let Some((index, _, _)) = self.entries.shift_remove_full(key) {
+            for (position, _) in &mut self.unlabeled {
+                if *position > index {
+                    *position -= 1;
+                }
+            }
+        }
+        self.aliases.remove(key);
+        self.dirty_keys.remove(key);
+    }
+
+    pub fn move_entry(&mut self, key: &str, new_index: usize) -> Result<()> {
+        let index = self.entries.get_index_of(key).ok_or_else(|| {
+            TextArchiveError::OtherError(format!("Key '{}' does not exist.", key))
+        })?;
+        if new_index >= self.entries.len() {
+            return Err(TextArchiveError::OtherError(format!(
+                "Index {} is out of range for {} entries.",
+                new_index,
+                self.entries.len()
+            )));
+        }
+        if index != new_index {
+            self.entries.move_index(index, new_index);
+            self.dirty = true;
+        }
+        Ok(())
     }
 
     pub fn get_message(&self, key: &str) -> Option<String> {
         self.entries.get(key).map(|value| value.replace('\n', "\\n"))
     }
 
+    pub fn get_message_or_key(&self, key: &str) -> String {
+        self.get_message(key).unwrap_or_else(|| key.to_string())
+    }
+
     pub fn set_message(&mut self, key: &str, message: &str) {
-        let message = message.replace("\\n", "\n");
-        let entry = self.entries.entry(key.to_string()).or_default();
-        *entry = message;
+        self.update_message(key.to_string(), unescape_message(message));
         self.dirty = true;
     }
 
+    // Only records the key as dirty if the message actually changed.
+    fn update_message(&mut self, key: String, message: String) {
+        let changed = self.entries.get(&key) != Some(&message);
+        if changed {
+            self.dirty_keys.insert(key.clone());
+        }
+        self.entries.insert(key, message);
+    }
+
+    pub fn set_messages(&mut self, entries: impl IntoIterator<Item = (String, String)>) {
+        let mut changed = false;
+        for (key, message) in entries {
+            self.update_message(key, unescape_message(&message));
+            changed = true;
+        }
+        if changed {
+            self.dirty = true;
+        }
+    }
+
     pub fn is_dirty(&self) -> bool {
         self.dirty
     }
+
+    // Keys whose messages changed since the archive was created, in archive order.
+    pub fn dirty_keys(&self) -> Vec<&str> {
+        self.entries
+            .keys()
+            .filter(|key| self.dirty_keys.contains(*key))
+            .map(|key| key.as_str())
+            .collect()
+    }
+
+    // Changes needed to turn this archive's messages into other's.
+    // Keys are visited in this archive's order, then keys only other has in its order.
+    pub fn diff(&self, other: &TextArchive) -> Vec<MessageDiff> {
+        let mut diffs = Vec::new();
+        for (key, old) in &self.entries {
+            match other.entries.get(key) {
+                Some(new) if new != old => diffs.push(MessageDiff::Changed {
+                    key: key.clone(),
+                    old: old.clone(),
+                    new: new.clone(),
+                }),
+                Some(_) => {}
+                None => diffs.push(MessageDiff::Removed(key.clone())),
+            }
+        }
+        for key in other.entries.keys() {
+            if !self.entries.contains_key(key) {
+                diffs.push(MessageDiff::Added(key.clone()));
+            }
+        }
+        diffs
+    }
 }
 
 #[cfg(test)]
@@ -149,6 +491,58 @@ mod test {
     use super::*;
     use crate::utils::load_test_file;
 
+    #[test]
+    fn diff() {
+        let entries = |pairs: &[(&str, &str)]| {
+            let entries: Vec<(String, String)> = pairs
+                .iter()
+                .map(|(key, message)| (key.to_string(), message.to_string()))
+                .collect();
+            TextArchive::from_entries(
+                String::new(),
+                entries,
+                TextArchiveFormat::Unicode,
+                Endian::Little,
+            )
+        };
+        let old = entries(&[("MID_A", "A"), ("MID_B", "B"), ("MID_C", "C")]);
+        let new = entries(&[("MID_D", "D"), ("MID_C", "C"), ("MID_A", "A2")]);
+        let expected = vec![
+            MessageDiff::Changed {
+                key: "MID_A".to_string(),
+                old: "A".to_string(),
+                new: "A2".to_string(),
+            },
+            MessageDiff::Removed("MID_B".to_string()),
+            MessageDiff::Added("MID_D".to_string()),
+        ];
+        assert_eq!(expected, old.diff(&new));
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn from_entries() {
+        let archive = TextArchive::from_entries(
+            "Title".to_string(),
+            vec![
+                ("MID_B".to_string(), "Line\\n".to_string()),
+                ("MID_A".to_string(), "Second".to_string()),
+            ],
+            TextArchiveFormat::Unicode,
+            Endian::Little,
+        );
+        assert_eq!("Title", archive.get_title());
+        assert_eq!(Some("Line\\n".to_string()), archive.get_message("MID_B"));
+        let keys: Vec<&String> = archive.get_entries().keys().collect();
+        assert_eq!(vec!["MID_B", "MID_A"], keys);
+        assert!(!archive.is_dirty());
+
+        let bytes = archive.serialize().unwrap();
+        let result =
+            TextArchive::from_bytes(&bytes, TextArchiveFormat::Unicode, Endian::Little).unwrap();
+        assert_eq!(Some("Second".to_string()), result.get_message("MID_A"));
+    }
+
     #[test]
     fn round_trip_serialization_unicode() {
         let bytes = load_test_file("TextArchive_Test.bin");
@@ -161,6 +555,53 @@ mod test {
         assert_eq!(serialized_bytes, bytes);
     }
 
+    #[test]
+    fn round_trip_serialization_unicode_big_endian() {
+        let mut archive = TextArchive::new(TextArchiveFormat::Unicode, Endian::Big);
+        archive.set_message("MID_A", "Aあ");
+        archive.set_message("MID_B", "Second");
+        let bytes = archive.serialize().unwrap();
+        let text_archive =
+            TextArchive::from_bytes(&bytes, TextArchiveFormat::Unicode, Endian::Big).unwrap();
+        assert_eq!(Some("Aあ".to_string()), text_archive.get_message("MID_A"));
+        assert_eq!(
+            Some("Second".to_string()),
+            text_archive.get_message("MID_B")
+        );
+        assert_eq!(bytes, text_archive.serialize().unwrap());
+    }
+
+    #[test]
+    fn detect_format() {
+        let bytes = load_test_file("TextArchive_Test.bin");
+        assert!(matches!(
+            TextArchive::detect_format(&bytes, Endian::Little),
+            Some(TextArchiveFormat::Unicode)
+        ));
+        let bytes = load_test_file("TextArchive_Legacy_Test.bin");
+        assert!(matches!(
+            TextArchive::detect_format(&bytes, Endian::Big),
+            Some(TextArchiveFormat::ShiftJIS)
+        ));
+        let bytes = build_grouped_legacy_archive();
+        assert!(matches!(
+            TextArchive::detect_format(&bytes, Endian::Big),
+            Some(TextArchiveFormat::ShiftJIS)
+        ));
+
+        // No label at the start, like FE10's group delimiters.
+        let mut archive = TextArchive::new(TextArchiveFormat::ShiftJIS, Endian::Big);
+        archive.push_message(Vec::new(), "Group".to_string());
+        archive.push_message(vec!["MID_A".to_string()], "Hey".to_string());
+        archive.push_message(vec!["MID_B".to_string()], "Hello".to_string());
+        let bytes = archive.serialize().unwrap();
+        assert!(matches!(
+            TextArchive::detect_format(&bytes, Endian::Big),
+            Some(TextArchiveFormat::ShiftJIS)
+        ));
+        assert!(TextArchive::detect_format(&[0; 4], Endian::Little).is_none());
+    }
+
     #[test]
     fn round_trip_serialization_shift_jis() {
         let bytes = load_test_file("TextArchive_Legacy_Test.bin");
@@ -171,6 +612,109 @@ mod test {
         assert_eq!(serialized_bytes, bytes);
     }
 
+    #[test]
+    fn round_trip_serialization_legacy_bytes() {
+        let bytes = load_test_file("TextArchive_Legacy_Test.bin");
+        let text_archive = TextArchive::from_legacy_bytes(&bytes, Endian::Big).unwrap();
+        let expected =
+            TextArchive::from_bytes(&bytes, TextArchiveFormat::ShiftJIS, Endian::Big).unwrap();
+        assert_eq!(expected.get_entries(), text_archive.get_entries());
+        let serialized_bytes = text_archive.serialize().unwrap();
+        assert_eq!(serialized_bytes, bytes);
+    }
+
+    fn build_grouped_legacy_archive() -> Vec<u8> {
+        let mut archive = BinArchive::new(Endian::Big);
+        archive.allocate_at_end(16);
+        archive
+            .write_bytes(0, b"A\0\0\0\0\0\0\0B\0\0\0C\0\0\0")
+            .unwrap();
+        archive.write_label(0, "MID_A").unwrap();
+        archive.write_label(8, "MID_B").unwrap();
+        archive.write_label(8, "MID_B_ALIAS").unwrap();
+        archive.write_label(12, "MID_C").unwrap();
+        archive.serialize().unwrap()
+    }
+
+    #[test]
+    fn round_trip_serialization_legacy_groups() {
+        let bytes = build_grouped_legacy_archive();
+        let text_archive =
+            TextArchive::from_bytes(&bytes, TextArchiveFormat::ShiftJIS, Endian::Big).unwrap();
+        assert_eq!(3, text_archive.get_entries().len());
+        assert_eq!(bytes, text_archive.serialize().unwrap());
+        assert_eq!(Some(8), text_archive.offset_of("MID_B"));
+
+        let text_archive = TextArchive::from_legacy_bytes(&bytes, Endian::Big).unwrap();
+        assert_eq!(bytes, text_archive.serialize().unwrap());
+    }
+
+    #[test]
+    fn delete_message_keeps_group_separator() {
+        let bytes = build_grouped_legacy_archive();
+        let mut text_archive = TextArchive::from_legacy_bytes(&bytes, Endian::Big).unwrap();
+        text_archive.delete_message("MID_A");
+        assert_eq!(Some(4), text_archive.offset_of("MID_B"));
+    }
+
+    #[test]
+    fn offset_of() {
+        let bytes = load_test_file("TextArchive_Test.bin");
+        let text_archive =
+            TextArchive::from_bytes(&bytes, TextArchiveFormat::Unicode, Endian::Little).unwrap();
+        let archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        for key in text_archive.get_entries().keys() {
+            assert_eq!(archive.find_label_address(key), text_archive.offset_of(key));
+        }
+        assert!(text_archive.offset_of("not_a_key").is_none());
+    }
+
+    #[test]
+    fn message_bytes() {
+        let check = |file: &str, format: TextArchiveFormat, endian: Endian| {
+            let bytes = load_test_file(file);
+            let text_archive = TextArchive::from_bytes(&bytes, format, endian).unwrap();
+            for key in text_archive.get_entries().keys() {
+                let message = text_archive.message_bytes(key).unwrap().unwrap();
+                let offset = text_archive.offset_of(key).unwrap() + 0x20;
+                assert_eq!(&bytes[offset..offset + message.len()], &message[..]);
+            }
+            assert!(text_archive.message_bytes("not_a_key").unwrap().is_none());
+        };
+        check(
+            "TextArchive_Test.bin",
+            TextArchiveFormat::Unicode,
+            Endian::Little,
+        );
+        check(
+            "TextArchive_Legacy_Test.bin",
+            TextArchiveFormat::ShiftJIS,
+            Endian::Big,
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let mut archive = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
+        archive.set_message("MID_Test", "Test");
+        assert!(archive.validate().is_ok());
+
+        archive
+            .aliases
+            .insert("MID_Test".to_string(), vec!["MID_Test".to_string()]);
+        assert!(matches!(
+            archive.validate(),
+            Err(TextArchiveError::OtherError(_))
+        ));
+        archive.aliases.clear();
+
+        archive.set_message("", "Empty");
+        assert!(matches!(
+            archive.validate(),
+            Err(TextArchiveError::MissingKey)
+        ));
+    }
+
     #[test]
     fn get_message() {
         let mut text_archive = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
@@ -183,6 +727,16 @@ mod test {
         assert_eq!(message.unwrap(), "My message\\nhas newlines\\n.");
     }
 
+    #[test]
+    fn get_message_or_key() {
+        let mut text_archive = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
+        text_archive
+            .entries
+            .insert("my_key".to_string(), "My message".to_string());
+        assert_eq!(text_archive.get_message_or_key("my_key"), "My message");
+        assert_eq!(text_archive.get_message_or_key("missing"), "missing");
+    }
+
     #[test]
     fn set_message() {
         let mut text_archive = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
@@ -214,4 +768,133 @@ mod test {
         let keys: Vec<String> = archive.entries.keys().cloned().collect();
         assert_eq!(vec!["Key1".to_string(), "Key2".to_string()], keys);
     }
+
+    #[test]
+    fn set_messages() {
+        let mut archive = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
+        archive.set_messages(Vec::new());
+        assert!(!archive.is_dirty());
+
+        archive.set_message("Key2", "Old");
+        archive.set_messages(vec![
+            ("Key3".to_string(), "Three\\n".to_string()),
+            ("Key2".to_string(), "Two".to_string()),
+            ("Key1".to_string(), "One".to_string()),
+        ]);
+        assert!(archive.is_dirty());
+        let keys: Vec<&String> = archive.entries.keys().collect();
+        assert_eq!(vec!["Key2", "Key3", "Key1"], keys);
+        assert_eq!("Three\n", archive.entries.get("Key3").unwrap());
+        assert_eq!("Two", archive.entries.get("Key2").unwrap());
+    }
+
+    #[test]
+    fn set_message_normalizes_line_endings() {
+        let mut lf = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
+        lf.set_message("MID_A", "One\nTwo\\nThree");
+        let mut crlf = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
+        crlf.set_message("MID_A", "One\r\nTwo\\nThree");
+        let mut cr = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
+        cr.set_messages(vec![("MID_A".to_string(), "One\rTwo\\nThree".to_string())]);
+        assert_eq!("One\nTwo\nThree", crlf.entries.get("MID_A").unwrap());
+        assert_eq!(lf.serialize().unwrap(), crlf.serialize().unwrap());
+        assert_eq!(lf.serialize().unwrap(), cr.serialize().unwrap());
+    }
+
+    #[test]
+    fn split_by_prefix() {
+        let mut archive = TextArchive::from_entries(
+            "Title".to_string(),
+            vec![
+                ("MID_CHAP2_A".to_string(), "Two A".to_string()),
+                ("MID_CHAP1_B".to_string(), "One B".to_string()),
+                ("MID_CHAP1_A".to_string(), "One A".to_string()),
+            ],
+            TextArchiveFormat::Unicode,
+            Endian::Little,
+        );
+        archive.set_message("MID_CHAP1_A", "Edited");
+        let chapter = archive.split_by_prefix("MID_CHAP1_");
+        assert!(!chapter.is_dirty());
+        assert_eq!("Title", chapter.get_title());
+        let keys: Vec<&String> = chapter.entries.keys().collect();
+        assert_eq!(vec!["MID_CHAP1_B", "MID_CHAP1_A"], keys);
+        assert_eq!("Edited", chapter.entries.get("MID_CHAP1_A").unwrap());
+        assert!(archive.split_by_prefix("MID_CHAP3_").entries.is_empty());
+    }
+
+    #[test]
+    fn move_entry() {
+        let mut archive = TextArchive::from_entries(
+            "Title".to_string(),
+            vec![
+                ("Key1".to_string(), "One".to_string()),
+                ("Key2".to_string(), "Two".to_string()),
+                ("Key3".to_string(), "Three".to_string()),
+            ],
+            TextArchiveFormat::Unicode,
+            Endian::Little,
+        );
+        archive.move_entry("Key3", 0).unwrap();
+        assert!(archive.is_dirty());
+        let keys: Vec<&String> = archive.entries.keys().collect();
+        assert_eq!(vec!["Key3", "Key1", "Key2"], keys);
+        archive.move_entry("Key3", 2).unwrap();
+        let keys: Vec<&String> = archive.entries.keys().collect();
+        assert_eq!(vec!["Key1", "Key2", "Key3"], keys);
+        assert!(archive.move_entry("Missing", 0).is_err());
+        assert!(archive.move_entry("Key1", 3).is_err());
+    }
+
+    #[test]
+    fn dirty_keys() {
+        let mut archive = TextArchive::new(TextArchiveFormat::Unicode, Endian::Little);
+        archive
+            .entries
+            .insert("Key1".to_string(), "Value1".to_string());
+        archive
+            .entries
+            .insert("Key2".to_string(), "Value2".to_string());
+        assert!(archive.dirty_keys().is_empty());
+
+        archive.set_message("Key2", "NewValue");
+        archive.set_message("Key1", "Value1");
+        archive.set_messages(vec![("Key3".to_string(), "Value3".to_string())]);
+        assert_eq!(vec!["Key2", "Key3"], archive.dirty_keys());
+
+        archive.delete_message("Key2");
+        assert_eq!(vec!["Key3"], archive.dirty_keys());
+    }
+
+    #[test]
+    fn serialize_with_alignment() {
+        let mut archive = TextArchive::new(TextArchiveFormat::ShiftJIS, Endian::Little);
+        archive.set_alignment(8);
+        archive.set_message("MID_A", "A");
+        archive.set_message("MID_B", "B");
+        assert_eq!(Some(8), archive.offset_of("MID_B"));
+
+        let bytes = archive.serialize().unwrap();
+        let bin_archive = BinArchive::from_bytes(&bytes, Endian::Little).unwrap();
+        assert_eq!(16, bin_archive.size());
+        assert_eq!(Some(8), bin_archive.find_label_address("MID_B"));
+    }
+
+    #[test]
+    fn round_trip_serialization_with_alignment() {
+        for format in [TextArchiveFormat::ShiftJIS, TextArchiveFormat::Unicode] {
+            let mut archive = TextArchive::new(format, Endian::Little);
+            archive.set_alignment(8);
+            archive.set_message("MID_A", "A");
+            archive.set_message("MID_B", "B");
+            let bytes = archive.serialize().unwrap();
+
+            let text_archive =
+                TextArchive::from_bytes_with_alignment(&bytes, format, Endian::Little, 8).unwrap();
+            assert_eq!(8, text_archive.alignment());
+            assert!(text_archive.unlabeled.is_empty());
+            assert_eq!(Some("B".to_string()), text_archive.get_message("MID_B"));
+            assert_eq!(bytes, text_archive.serialize().unwrap());
+        }
+    }
 }
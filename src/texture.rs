@@ -1,6 +1,555 @@
+use crate::texture_decoder::HardwareTextureFormat;
+use crate::tpl::TplImageFormat;
+use crate::TextureDecodeError;
+
+type Result<T> = std::result::Result<T, TextureDecodeError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureSourceFormat {
+    // Format used by BCH, CTPK and CGFX.
+    Pica(HardwareTextureFormat),
+    Tpl(TplImageFormat),
+}
+
+#[derive(Clone, Default)]
 pub struct Texture {
     pub filename: String,
     pub height: usize,
     pub width: usize,
     pub pixel_data: Vec<u8>,
+    // Encoded bytes the texture was decoded from, if it came from a container.
+    // Not kept in sync with pixel_data, so it only reflects the texture as read.
+    pub raw: Vec<u8>,
+    pub source_format: Option<TextureSourceFormat>,
+}
+
+impl Texture {
+    // Box filter: each output pixel averages the block of source pixels it covers.
+    // When upscaling the block is a single pixel, so this degrades to nearest neighbor.
+    pub fn resized(&self, new_width: usize, new_height: usize) -> Result<Texture> {
+        if new_width == 0 || new_height == 0 {
+            return Err(TextureDecodeError::InvalidDimensions {
+                width: new_width,
+                height: new_height,
+            });
+        }
+        if self.width == 0
+            || self.height == 0
+            || self.pixel_data.len() != self.width * self.height * 4
+        {
+            return Err(TextureDecodeError::BadDimensions {
+                width: self.width,
+                height: self.height,
+                expected_len: self.width * self.height * 4,
+                actual_len: self.pixel_data.len(),
+            });
+        }
+
+        let mut pixel_data: Vec<u8> = vec![0; new_width * new_height * 4];
+        for y in 0..new_height {
+            let start_y = y * self.height / new_height;
+            let end_y = ((y + 1) * self.height / new_height).max(start_y + 1);
+            for x in 0..new_width {
+                let start_x = x * self.width / new_width;
+                let end_x = ((x + 1) * self.width / new_width).max(start_x + 1);
+                let mut totals = [0usize; 4];
+                for source_y in start_y..end_y {
+                    for source_x in start_x..end_x {
+                        let index = (source_y * self.width + source_x) * 4;
+                        for (total, value) in
+                            totals.iter_mut().zip(&self.pixel_data[index..index + 4])
+                        {
+                            *total += *value as usize;
+                        }
+                    }
+                }
+                let count = (end_y - start_y) * (end_x - start_x);
+                let output_index = (y * new_width + x) * 4;
+                for (i, total) in totals.iter().enumerate() {
+                    pixel_data[output_index + i] = (total / count) as u8;
+                }
+            }
+        }
+        Ok(Texture {
+            filename: self.filename.clone(),
+            height: new_height,
+            width: new_width,
+            pixel_data,
+            ..Default::default()
+        })
+    }
+
+    // Copies out the w x h region whose top-left corner is (x, y).
+    // The crop is named after its source and region, ex. "atlas_8_0_4x4".
+    pub fn crop(&self, x: usize, y: usize, w: usize, h: usize) -> Result<Texture> {
+        if self.pixel_data.len() != self.width * self.height * 4 {
+            return Err(TextureDecodeError::BadDimensions {
+                width: self.width,
+                height: self.height,
+                expected_len: self.width * self.height * 4,
+                actual_len: self.pixel_data.len(),
+            });
+        }
+        let in_bounds = x.checked_add(w).is_some_and(|right| right <= self.width)
+            && y.checked_add(h).is_some_and(|bottom| bottom <= self.height);
+        if !in_bounds {
+            return Err(TextureDecodeError::RegionOutOfBounds {
+                x,
+                y,
+                width: w,
+                height: h,
+                texture_width: self.width,
+                texture_height: self.height,
+            });
+        }
+
+        let mut pixel_data: Vec<u8> = Vec::with_capacity(w * h * 4);
+        for row in y..y + h {
+            let start = (row * self.width + x) * 4;
+            pixel_data.extend_from_slice(&self.pixel_data[start..start + w * 4]);
+        }
+        Ok(Texture {
+            filename: format!("{}_{}_{}_{}x{}", self.filename, x, y, w, h),
+            height: h,
+            width: w,
+            pixel_data,
+            ..Default::default()
+        })
+    }
+
+    pub fn flip_vertical(&mut self) {
+        let stride = self.width * 4;
+        if stride == 0 {
+            return;
+        }
+        let rows = self.pixel_data.len() / stride;
+        for row in 0..rows / 2 {
+            let (top, bottom) = self.pixel_data.split_at_mut((rows - row - 1) * stride);
+            top[row * stride..(row + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+        }
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        let stride = self.width * 4;
+        if stride == 0 {
+            return;
+        }
+        // Reversing the bytes reverses the pixels but also each pixel's channels, so undo that.
+        for row in self.pixel_data.chunks_exact_mut(stride) {
+            row.reverse();
+            for pixel in row.chunks_exact_mut(4) {
+                pixel.reverse();
+            }
+        }
+    }
+
+    fn pixel_index(&self, x: usize, y: usize) -> Result<usize> {
+        if x >= self.width || y >= self.height {
+            return Err(TextureDecodeError::PixelOutOfBounds {
+                x,
+                y,
+                width: self.width,
+                height: self.height,
+            });
+        }
+        let index = (y * self.width + x) * 4;
+        if index + 4 > self.pixel_data.len() {
+            return Err(TextureDecodeError::BadDimensions {
+                width: self.width,
+                height: self.height,
+                expected_len: self.width * self.height * 4,
+                actual_len: self.pixel_data.len(),
+            });
+        }
+        Ok(index)
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Result<[u8; 4]> {
+        let index = self.pixel_index(x, y)?;
+        let mut rgba = [0; 4];
+        rgba.copy_from_slice(&self.pixel_data[index..index + 4]);
+        Ok(rgba)
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, rgba: [u8; 4]) -> Result<()> {
+        let index = self.pixel_index(x, y)?;
+        self.pixel_data[index..index + 4].copy_from_slice(&rgba);
+        Ok(())
+    }
+
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.pixel_data
+            .chunks_exact(4)
+            .flat_map(|pixel| pixel[..3].to_vec())
+            .collect()
+    }
+
+    pub fn opaque(&self) -> bool {
+        self.pixel_data
+            .chunks_exact(4)
+            .all(|pixel| pixel[3] == 0xFF)
+    }
+
+    pub fn premultiply_alpha(&mut self) {
+        for pixel in self.pixel_data.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            for channel in &mut pixel[0..3] {
+                *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+            }
+        }
+    }
+
+    // Color can't be recovered from fully transparent pixels, so they become transparent black.
+    pub fn unpremultiply_alpha(&mut self) {
+        for pixel in self.pixel_data.chunks_exact_mut(4) {
+            let alpha = pixel[3] as u32;
+            for channel in &mut pixel[0..3] {
+                let value = (*channel as u32 * 255 + alpha / 2).checked_div(alpha);
+                *channel = value.unwrap_or(0).min(255) as u8;
+            }
+        }
+    }
+
+    // Grayscale copy of the alpha channel. The mask itself is fully opaque.
+    pub fn alpha_mask(&self) -> Texture {
+        let pixel_data = self
+            .pixel_data
+            .chunks_exact(4)
+            .flat_map(|pixel| vec![pixel[3], pixel[3], pixel[3], 0xFF])
+            .collect();
+        Texture {
+            filename: self.filename.clone(),
+            height: self.height,
+            width: self.width,
+            pixel_data,
+            ..Default::default()
+        }
+    }
+
+    // Replaces alpha with the mask's red channel. The mask must have the same dimensions.
+    pub fn apply_alpha_mask(&mut self, mask: &Texture) -> Result<()> {
+        if mask.width != self.width
+            || mask.height != self.height
+            || mask.pixel_data.len() != self.pixel_data.len()
+        {
+            return Err(TextureDecodeError::BadDimensions {
+                width: mask.width,
+                height: mask.height,
+                expected_len: self.pixel_data.len(),
+                actual_len: mask.pixel_data.len(),
+            });
+        }
+        for (pixel, mask_pixel) in self
+            .pixel_data
+            .chunks_exact_mut(4)
+            .zip(mask.pixel_data.chunks_exact(4))
+        {
+            pixel[3] = mask_pixel[0];
+        }
+        Ok(())
+    }
+}
+
+// Every atlas cell is as large as the largest sprite, so sprite i's top-left corner is
+// ((i % columns) * cell_width, (i / columns) * cell_height). Rows grow downward.
+// A column count of 0 is treated as 1.
+pub fn atlas_positions(textures: &[Texture], columns: usize) -> Vec<(usize, usize)> {
+    let cell_width = textures.iter().map(|t| t.width).max().unwrap_or(0);
+    let cell_height = textures.iter().map(|t| t.height).max().unwrap_or(0);
+    let columns = columns.max(1);
+    (0..textures.len())
+        .map(|i| ((i % columns) * cell_width, (i / columns) * cell_height))
+        .collect()
+}
+
+// Lays sprites out in a grid of the given column count. See atlas_positions for where each lands.
+// Space not covered by a sprite is left transparent.
+pub fn pack_atlas(textures: &[Texture], columns: usize) -> Result<Texture> {
+    let cell_width = textures.iter().map(|t| t.width).max().unwrap_or(0);
+    let cell_height = textures.iter().map(|t| t.height).max().unwrap_or(0);
+    let columns = columns.max(1).min(textures.len());
+    let rows = textures.len().div_ceil(columns.max(1));
+    if columns == 0 || cell_width == 0 || cell_height == 0 {
+        return Err(TextureDecodeError::InvalidDimensions {
+            width: cell_width * columns,
+            height: cell_height * rows,
+        });
+    }
+
+    let width = cell_width * columns;
+    let height = cell_height * rows;
+    let mut pixel_data: Vec<u8> = vec![0; width * height * 4];
+    for (texture, (x, y)) in textures.iter().zip(atlas_positions(textures, columns)) {
+        if texture.pixel_data.len() != texture.width * texture.height * 4 {
+            return Err(TextureDecodeError::BadDimensions {
+                width: texture.width,
+                height: texture.height,
+                expected_len: texture.width * texture.height * 4,
+                actual_len: texture.pixel_data.len(),
+            });
+        }
+        let row_length = texture.width * 4;
+        if row_length == 0 {
+            continue;
+        }
+        for (row, source) in texture.pixel_data.chunks_exact(row_length).enumerate() {
+            let start = ((y + row) * width + x) * 4;
+            pixel_data[start..start + row_length].copy_from_slice(source);
+        }
+    }
+    Ok(Texture {
+        width,
+        height,
+        pixel_data,
+        ..Default::default()
+    })
+}
+
+// Containers that can be read into textures.
+pub fn supported_read_formats() -> Vec<&'static str> {
+    vec!["BCH", "CGFX", "CTPK", "TPL"]
+}
+
+// Containers that can be written from textures.
+pub fn supported_write_formats() -> Vec<&'static str> {
+    vec!["CTPK", "TPL"]
+}
+
+#[cfg(feature = "image")]
+impl Texture {
+    pub fn from_rgba_image(filename: String, image: image::RgbaImage) -> Self {
+        Texture {
+            filename,
+            height: image.height() as usize,
+            width: image.width() as usize,
+            pixel_data: image.into_raw(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<Texture> for image::RgbaImage {
+    fn from(texture: Texture) -> Self {
+        // Pad or trim so a malformed texture can't make the conversion fail.
+        let mut pixel_data = texture.pixel_data;
+        pixel_data.resize(texture.width * texture.height * 4, 0);
+        image::RgbaImage::from_raw(texture.width as u32, texture.height as u32, pixel_data).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ctpk, TextureWriteOptions};
+
+    #[test]
+    fn pack_atlas_grid() {
+        let sprite = |value: u8, width: usize, height: usize| Texture {
+            width,
+            height,
+            pixel_data: vec![value; width * height * 4],
+            ..Default::default()
+        };
+        let textures = vec![sprite(1, 2, 2), sprite(2, 2, 2), sprite(3, 1, 1)];
+        assert_eq!(vec![(0, 0), (2, 0), (0, 2)], atlas_positions(&textures, 2));
+
+        let atlas = pack_atlas(&textures, 2).unwrap();
+        assert_eq!((4, 4), (atlas.width, atlas.height));
+        let pixel = |x: usize, y: usize| atlas.pixel_data[(y * atlas.width + x) * 4];
+        assert_eq!(1, pixel(1, 1));
+        assert_eq!(2, pixel(2, 0));
+        assert_eq!(3, pixel(0, 2));
+        assert_eq!(0, pixel(1, 2));
+        assert_eq!(0, pixel(3, 3));
+
+        assert_eq!(vec![(0, 0), (0, 2), (0, 4)], atlas_positions(&textures, 0));
+        let column = pack_atlas(&textures, 0).unwrap();
+        assert_eq!((2, 6), (column.width, column.height));
+        assert_eq!(
+            pack_atlas(&textures, 1).unwrap().pixel_data,
+            column.pixel_data
+        );
+        assert!(matches!(
+            pack_atlas(&[], 2),
+            Err(TextureDecodeError::InvalidDimensions { .. })
+        ));
+        assert!(pack_atlas(&[sprite(1, 2, 2), sprite(1, 0, 0)], 4).is_ok());
+        let mut bad = sprite(1, 2, 2);
+        bad.pixel_data.pop();
+        assert!(pack_atlas(&[bad], 1).is_err());
+    }
+
+    #[test]
+    fn supported_formats() {
+        for format in supported_write_formats() {
+            assert!(supported_read_formats().contains(&format));
+        }
+        let texture = test_texture().resized(8, 8).unwrap();
+        assert!(ctpk::write(&[texture], &TextureWriteOptions::default()).is_ok());
+    }
+
+    fn test_texture() -> Texture {
+        Texture {
+            filename: "test".to_string(),
+            height: 2,
+            width: 2,
+            pixel_data: vec![
+                0, 0, 0, 255, 100, 100, 100, 255, //
+                200, 200, 200, 255, 100, 100, 100, 255,
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn get_set_pixel() {
+        let mut texture = test_texture();
+        assert_eq!([100, 100, 100, 255], texture.get_pixel(1, 1).unwrap());
+        texture.set_pixel(1, 1, [1, 2, 3, 4]).unwrap();
+        assert_eq!([1, 2, 3, 4], texture.get_pixel(1, 1).unwrap());
+        assert_eq!(vec![1, 2, 3, 4], texture.pixel_data[12..16].to_vec());
+
+        assert!(texture.get_pixel(2, 0).is_err());
+        assert!(texture.set_pixel(0, 2, [0; 4]).is_err());
+    }
+
+    #[test]
+    fn to_rgb_opaque() {
+        let texture = test_texture();
+        assert!(texture.opaque());
+        assert_eq!(2 * 2 * 3, texture.to_rgb().len());
+        assert_eq!(vec![0, 0, 0, 100, 100, 100], texture.to_rgb()[..6].to_vec());
+    }
+
+    #[test]
+    fn to_rgb_transparent() {
+        let mut texture = test_texture();
+        texture.pixel_data[7] = 0x80;
+        assert!(!texture.opaque());
+        assert_eq!(vec![0, 0, 0, 100, 100, 100], texture.to_rgb()[..6].to_vec());
+    }
+
+    #[test]
+    fn flip() {
+        // 3x2, with pixel values 1-6 in row-major order.
+        let mut flipped = Texture {
+            width: 3,
+            height: 2,
+            pixel_data: (1..=6).flat_map(|i| vec![i, i, i, 10 + i]).collect(),
+            ..Default::default()
+        };
+        let order = |texture: &Texture| -> Vec<u8> {
+            texture.pixel_data.chunks(4).map(|pixel| pixel[0]).collect()
+        };
+
+        flipped.flip_vertical();
+        assert_eq!(vec![4, 5, 6, 1, 2, 3], order(&flipped));
+        flipped.flip_horizontal();
+        assert_eq!(vec![6, 5, 4, 3, 2, 1], order(&flipped));
+        assert_eq!(vec![6, 6, 6, 16], flipped.pixel_data[0..4].to_vec());
+    }
+
+    #[test]
+    fn crop() {
+        // Each pixel holds its own coordinates so crops are easy to check.
+        let pixel_data: Vec<u8> = (0..8 * 8)
+            .flat_map(|i| vec![(i % 8) as u8, (i / 8) as u8, 0, 255])
+            .collect();
+        let texture = Texture {
+            filename: "atlas".to_string(),
+            width: 8,
+            height: 8,
+            pixel_data,
+            ..Default::default()
+        };
+        let cropped = texture.crop(4, 2, 4, 4).unwrap();
+        assert_eq!("atlas_4_2_4x4", cropped.filename);
+        assert_eq!((4, 4), (cropped.width, cropped.height));
+        assert_eq!(4 * 4 * 4, cropped.pixel_data.len());
+        assert_eq!(vec![4, 2, 0, 255], cropped.pixel_data[0..4].to_vec());
+        assert_eq!(vec![7, 5, 0, 255], cropped.pixel_data[60..64].to_vec());
+
+        assert!(texture.crop(5, 0, 4, 4).is_err());
+        assert!(texture.crop(0, usize::MAX, 1, 1).is_err());
+        assert!(matches!(
+            texture.crop(0, 0, usize::MAX, usize::MAX),
+            Err(TextureDecodeError::RegionOutOfBounds { .. })
+        ));
+        assert!(texture.crop(0, 0, 8, 8).is_ok());
+    }
+
+    #[test]
+    fn alpha_mask_round_trip() {
+        let mut texture = test_texture();
+        texture.pixel_data[3] = 0x40;
+        let mask = texture.alpha_mask();
+        assert_eq!(vec![0x40, 0x40, 0x40, 0xFF], mask.pixel_data[..4].to_vec());
+        assert_eq!(vec![0xFF, 0xFF, 0xFF, 0xFF], mask.pixel_data[4..8].to_vec());
+
+        let mut target = test_texture();
+        target.apply_alpha_mask(&mask).unwrap();
+        assert_eq!(texture.pixel_data, target.pixel_data);
+        let small = test_texture().resized(1, 1).unwrap();
+        assert!(target.apply_alpha_mask(&small).is_err());
+    }
+
+    #[test]
+    fn resized_downscale() {
+        let texture = test_texture().resized(1, 1).unwrap();
+        assert_eq!(1, texture.width);
+        assert_eq!(1, texture.height);
+        assert_eq!(vec![100, 100, 100, 255], texture.pixel_data);
+    }
+
+    #[test]
+    fn resized_upscale() {
+        let texture = test_texture().resized(4, 3).unwrap();
+        assert_eq!(4 * 3 * 4, texture.pixel_data.len());
+        assert_eq!(&[0, 0, 0, 255], &texture.pixel_data[0..4]);
+    }
+
+    #[test]
+    fn premultiply_alpha() {
+        let mut texture = Texture {
+            filename: "test".to_string(),
+            height: 1,
+            width: 3,
+            pixel_data: vec![200, 100, 50, 255, 200, 100, 50, 128, 200, 100, 50, 0],
+            ..Default::default()
+        };
+        texture.premultiply_alpha();
+        assert_eq!(
+            vec![200, 100, 50, 255, 100, 50, 25, 128, 0, 0, 0, 0],
+            texture.pixel_data
+        );
+        texture.unpremultiply_alpha();
+        assert_eq!(
+            vec![200, 100, 50, 255, 199, 100, 50, 128, 0, 0, 0, 0],
+            texture.pixel_data
+        );
+    }
+
+    #[test]
+    fn resized_zero_dimensions() {
+        assert!(matches!(
+            test_texture().resized(0, 4),
+            Err(TextureDecodeError::InvalidDimensions {
+                width: 0,
+                height: 4
+            })
+        ));
+        assert!(test_texture().resized(4, 0).is_err());
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn rgba_image_round_trip() {
+        let image: image::RgbaImage = test_texture().into();
+        assert_eq!(2, image.width());
+        assert_eq!(2, image.height());
+        assert_eq!(&[200, 200, 200, 255], &image.get_pixel(0, 1).0);
+        let texture = Texture::from_rgba_image("test".to_string(), image);
+        assert_eq!(test_texture().pixel_data, texture.pixel_data);
+    }
 }
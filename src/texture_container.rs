@@ -0,0 +1,16 @@
+use crate::{HardwareTextureFormat, Texture, TextureParseError};
+
+type Result<T> = std::result::Result<T, TextureParseError>;
+
+#[derive(Debug, Clone, Default)]
+pub struct TextureWriteOptions {
+    pub pixel_format: HardwareTextureFormat,
+    // RGBA palette for containers that only write indexed formats, like TPL.
+    pub palette: Option<Vec<u8>>,
+}
+
+pub trait TextureContainer {
+    fn read(bytes: &[u8]) -> Result<Vec<Texture>>;
+
+    fn write(textures: &[Texture], options: &TextureWriteOptions) -> Result<Vec<u8>>;
+}
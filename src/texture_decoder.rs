@@ -1,5 +1,6 @@
 use crate::{etc1, TextureDecodeError};
 use byteorder::{LittleEndian, ReadBytesExt};
+use std::borrow::Cow;
 use std::io::{Cursor, Seek, SeekFrom};
 
 static CONVERT_5_TO_8: &[u8] = &[
@@ -15,39 +16,118 @@ static TILE_ORDER: &[u8] = &[
 
 type Result<T> = std::result::Result<T, TextureDecodeError>;
 
-pub fn decode_color(value: u32, format: u32) -> Vec<u8> {
+// Pixel formats shared by BCH, CTPK and CGFX. Discriminants are the raw values stored in files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HardwareTextureFormat {
+    #[default]
+    RGBA8 = 0,
+    RGB8 = 1,
+    RGBA5551 = 2,
+    RGB565 = 3,
+    RGBA4 = 4,
+    LA8 = 5,
+    HILO8 = 6,
+    L8 = 7,
+    A8 = 8,
+    LA4 = 9,
+    L4 = 10,
+    A4 = 11,
+    ETC1 = 12,
+    ETC1A4 = 13,
+}
+
+impl HardwareTextureFormat {
+    pub fn from_u32(value: u32) -> Option<Self> {
+        let format = match value {
+            0 => HardwareTextureFormat::RGBA8,
+            1 => HardwareTextureFormat::RGB8,
+            2 => HardwareTextureFormat::RGBA5551,
+            3 => HardwareTextureFormat::RGB565,
+            4 => HardwareTextureFormat::RGBA4,
+            5 => HardwareTextureFormat::LA8,
+            6 => HardwareTextureFormat::HILO8,
+            7 => HardwareTextureFormat::L8,
+            8 => HardwareTextureFormat::A8,
+            9 => HardwareTextureFormat::LA4,
+            10 => HardwareTextureFormat::L4,
+            11 => HardwareTextureFormat::A4,
+            12 => HardwareTextureFormat::ETC1,
+            13 => HardwareTextureFormat::ETC1A4,
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    pub fn to_u32(self) -> u32 {
+        self as u32
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            HardwareTextureFormat::RGBA8 => "RGBA8",
+            HardwareTextureFormat::RGB8 => "RGB8",
+            HardwareTextureFormat::RGBA5551 => "RGBA5551",
+            HardwareTextureFormat::RGB565 => "RGB565",
+            HardwareTextureFormat::RGBA4 => "RGBA4",
+            HardwareTextureFormat::LA8 => "LA8",
+            HardwareTextureFormat::HILO8 => "HILO8",
+            HardwareTextureFormat::L8 => "L8",
+            HardwareTextureFormat::A8 => "A8",
+            HardwareTextureFormat::LA4 => "LA4",
+            HardwareTextureFormat::L4 => "L4",
+            HardwareTextureFormat::A4 => "A4",
+            HardwareTextureFormat::ETC1 => "ETC1",
+            HardwareTextureFormat::ETC1A4 => "ETC1A4",
+        }
+    }
+
+    pub fn bytes_per_pixel(self) -> f32 {
+        match self {
+            HardwareTextureFormat::RGBA8 => 4.0,
+            HardwareTextureFormat::RGB8 => 3.0,
+            HardwareTextureFormat::RGBA5551
+            | HardwareTextureFormat::RGB565
+            | HardwareTextureFormat::RGBA4
+            | HardwareTextureFormat::LA8 => 2.0,
+            HardwareTextureFormat::HILO8
+            | HardwareTextureFormat::L8
+            | HardwareTextureFormat::A8
+            | HardwareTextureFormat::LA4
+            | HardwareTextureFormat::A4
+            | HardwareTextureFormat::ETC1A4 => 1.0,
+            HardwareTextureFormat::L4 | HardwareTextureFormat::ETC1 => 0.5,
+        }
+    }
+}
+
+pub fn decode_color(value: u32, format: HardwareTextureFormat) -> Vec<u8> {
     let mut color: Vec<u8> = vec![0, 0, 0, 0];
     match format {
-        0 => {
-            // RGBA8
+        HardwareTextureFormat::RGBA8 => {
             color[0] = ((value >> 24) & 0xFF) as u8;
             color[1] = ((value >> 16) & 0xFF) as u8;
             color[2] = ((value >> 8) & 0xFF) as u8;
             color[3] = (value & 0xFF) as u8;
         }
-        1 => {
-            // RGB8
+        HardwareTextureFormat::RGB8 => {
             color[0] = ((value >> 16) & 0xFF) as u8;
             color[1] = ((value >> 8) & 0xFF) as u8;
             color[2] = (value & 0xFF) as u8;
             color[3] = 0xFF;
         }
-        2 => {
-            // RGB 5551
+        HardwareTextureFormat::RGBA5551 => {
             color[0] = CONVERT_5_TO_8[((value >> 11) & 0x1F) as usize];
             color[1] = CONVERT_5_TO_8[((value >> 6) & 0x1F) as usize];
             color[2] = CONVERT_5_TO_8[((value >> 1) & 0x1F) as usize];
             color[3] = if value & 1 == 1 { 0xFF } else { 0 };
         }
-        3 => {
-            // RGB565
+        HardwareTextureFormat::RGB565 => {
             color[0] = CONVERT_5_TO_8[((value >> 11) & 0x1F) as usize];
             color[1] = (((value >> 5) & 0x3F) * 4) as u8;
             color[2] = CONVERT_5_TO_8[(value & 0x1F) as usize];
             color[3] = 0xFF;
         }
-        4 => {
-            // RGBA4
+        HardwareTextureFormat::RGBA4 => {
             let r = (value >> 12) & 0xF;
             let g = (value >> 8) & 0xF;
             let b = (value >> 4) & 0xF;
@@ -57,54 +137,47 @@ pub fn decode_color(value: u32, format: u32) -> Vec<u8> {
             color[2] = (b | (b << 4)) as u8;
             color[3] = (a | (a << 4)) as u8;
         }
-        5 => {
-            // LA8
+        HardwareTextureFormat::LA8 => {
             let red = ((value >> 8) & 0xFF) as u8;
             color[0] = red;
             color[1] = red;
             color[2] = red;
             color[3] = (value & 0xFF) as u8;
         }
-        6 => {
-            // HILO8
+        HardwareTextureFormat::HILO8 => {
             let red = (value >> 8) as u8;
             color[0] = red;
             color[1] = red;
             color[2] = red;
             color[3] = 0xFF;
         }
-        7 => {
-            // L8
+        HardwareTextureFormat::L8 => {
             color[0] = value as u8;
             color[1] = value as u8;
             color[2] = value as u8;
             color[3] = 0xFF;
         }
-        8 => {
-            // A8
+        HardwareTextureFormat::A8 => {
             color[0] = 0xFF;
             color[1] = 0xFF;
             color[2] = 0xFF;
             color[3] = value as u8;
         }
-        9 => {
-            // LA4
+        HardwareTextureFormat::LA4 => {
             let red = (value >> 4) as u8;
             color[0] = red;
             color[1] = red;
             color[2] = red;
             color[3] = (value & 0xF) as u8;
         }
-        10 => {
-            // L4
+        HardwareTextureFormat::L4 => {
             let red = (value * 0x11) as u8;
             color[0] = red;
             color[1] = red;
             color[2] = red;
             color[3] = 0xFF;
         }
-        11 => {
-            // A4
+        HardwareTextureFormat::A4 => {
             color[0] = 0xFF;
             color[1] = 0xFF;
             color[2] = 0xFF;
@@ -119,7 +192,7 @@ fn decode_rgba_pixel_data(
     data: &[u8],
     width: usize,
     height: usize,
-    format: u32,
+    format: HardwareTextureFormat,
 ) -> Result<Vec<u8>> {
     let num_pixels = width * height;
     let mut bmp: Vec<u8> = vec![0; 4 * num_pixels];
@@ -133,17 +206,25 @@ fn decode_rgba_pixel_data(
                 let output_index = (tile_x * 8 + x + ((tile_y * 8 + y) * width)) * 4;
 
                 let color = match format {
-                    0 => decode_color(cursor.read_u32::<LittleEndian>()?, format),
-                    1 => {
+                    HardwareTextureFormat::RGBA8 => {
+                        decode_color(cursor.read_u32::<LittleEndian>()?, format)
+                    }
+                    HardwareTextureFormat::RGB8 => {
                         let value = cursor.read_u32::<LittleEndian>()?;
                         let value = value & 0xFFFFFF;
                         cursor.seek(SeekFrom::Current(-1))?;
                         decode_color(value, format)
                     }
-                    2..=5 => {
+                    HardwareTextureFormat::RGBA5551
+                    | HardwareTextureFormat::RGB565
+                    | HardwareTextureFormat::RGBA4
+                    | HardwareTextureFormat::LA8 => {
                         decode_color(cursor.read_u16::<LittleEndian>()? as u32, format)
                     }
-                    6..=9 => decode_color(cursor.read_u8()? as u32, format),
+                    HardwareTextureFormat::HILO8
+                    | HardwareTextureFormat::L8
+                    | HardwareTextureFormat::A8
+                    | HardwareTextureFormat::LA4 => decode_color(cursor.read_u8()? as u32, format),
                     _ => decode_color(0, format),
                 };
                 bmp[output_index..output_index + 4].copy_from_slice(&color[..]);
@@ -153,23 +234,61 @@ fn decode_rgba_pixel_data(
     Ok(bmp)
 }
 
-pub fn decode_pixel_data(data: &[u8], width: usize, height: usize, format: u32) -> Result<Vec<u8>> {
+pub fn decode_pixel_data(
+    data: &[u8],
+    width: usize,
+    height: usize,
+    format: HardwareTextureFormat,
+) -> Result<Vec<u8>> {
     match format {
-        0..=11 => {
-            decode_rgba_pixel_data(data, width, height, format)
-        }
-        12 | 13 => etc1::decode(data, width, height, format == 13),
-        _ => Err(TextureDecodeError::UnsupportedFormat),
+        HardwareTextureFormat::ETC1 => etc1::decode(data, width, height, false),
+        HardwareTextureFormat::ETC1A4 => etc1::decode(data, width, height, true),
+        _ => decode_rgba_pixel_data(data, width, height, format),
+    }
+}
+
+// Unknown values keep the raw id so they can still be told apart in logs.
+pub fn pixel_format_name(value: u32) -> Cow<'static, str> {
+    match HardwareTextureFormat::from_u32(value) {
+        Some(format) => Cow::Borrowed(format.name()),
+        None => Cow::Owned(format!("Unknown(0x{:X})", value)),
     }
 }
 
-pub fn get_pixel_format_bpp(pixel_format: u32) -> f32 {
-    match pixel_format {
-        0x0 => 4.0,
-        0x1 => 3.0,
-        0x2..=0x5 => 2.0,
-        0x6 | 0x7 | 0x8 | 0x9 | 0xB | 0xD => 1.0,
-        0xA | 0xC => 0.5,
-        _ => 0.0,
+pub fn get_level_size(width: usize, height: usize, pixel_format: HardwareTextureFormat) -> usize {
+    (pixel_format.bytes_per_pixel() * width as f32 * height as f32) as usize
+}
+
+// Mip levels are stored back to back, each half the size of the previous one.
+pub fn get_mip_offset(
+    width: usize,
+    height: usize,
+    pixel_format: HardwareTextureFormat,
+    level: usize,
+) -> usize {
+    (0..level)
+        .map(|i| get_level_size(width >> i, height >> i, pixel_format))
+        .sum()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pixel_format_names() {
+        assert_eq!("RGBA8", pixel_format_name(0));
+        assert_eq!("RGB565", pixel_format_name(3));
+        assert_eq!("ETC1", pixel_format_name(12));
+        assert_eq!("Unknown(0x2A)", pixel_format_name(0x2A));
+    }
+
+    #[test]
+    fn hardware_texture_format_round_trip() {
+        for value in 0..=13 {
+            let format = HardwareTextureFormat::from_u32(value).unwrap();
+            assert_eq!(value, format.to_u32());
+        }
+        assert_eq!(None, HardwareTextureFormat::from_u32(14));
     }
 }
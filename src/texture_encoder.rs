@@ -0,0 +1,69 @@
+use crate::{HardwareTextureFormat, TextureDecodeError};
+
+// Same tile order used when decoding, see texture_decoder.
+static TILE_ORDER: &[u8] = &[
+    0, 1, 8, 9, 2, 3, 10, 11, 16, 17, 24, 25, 18, 19, 26, 27, 4, 5, 12, 13, 6, 7, 14, 15, 20, 21,
+    28, 29, 22, 23, 30, 31, 32, 33, 40, 41, 34, 35, 42, 43, 48, 49, 56, 57, 50, 51, 58, 59, 36, 37,
+    44, 45, 38, 39, 46, 47, 52, 53, 60, 61, 54, 55, 62, 63,
+];
+
+type Result<T> = std::result::Result<T, TextureDecodeError>;
+
+fn encode_color(color: &[u8], format: HardwareTextureFormat) -> Result<Vec<u8>> {
+    match format {
+        // Stored as a little endian u32 with red in the high byte.
+        HardwareTextureFormat::RGBA8 => Ok(vec![color[3], color[2], color[1], color[0]]),
+        _ => Err(TextureDecodeError::UnsupportedFormat),
+    }
+}
+
+pub fn encode_pixel_data(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    format: HardwareTextureFormat,
+) -> Result<Vec<u8>> {
+    if !width.is_multiple_of(8) || !height.is_multiple_of(8) {
+        return Err(TextureDecodeError::BadBlockSize);
+    }
+    if rgba.len() != width * height * 4 {
+        return Err(TextureDecodeError::BadDimensions {
+            width,
+            height,
+            expected_len: width * height * 4,
+            actual_len: rgba.len(),
+        });
+    }
+
+    let mut encoded: Vec<u8> = Vec::new();
+    for tile_y in 0..height / 8 {
+        for tile_x in 0..width / 8 {
+            for tile_index in TILE_ORDER {
+                let x = (tile_index % 8) as usize;
+                let y = (*tile_index as usize - x) / 8;
+                let input_index = (tile_x * 8 + x + ((tile_y * 8 + y) * width)) * 4;
+                encoded.extend(encode_color(&rgba[input_index..input_index + 4], format)?);
+            }
+        }
+    }
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::texture_decoder::decode_pixel_data;
+
+    #[test]
+    fn rgba8_round_trip() {
+        let rgba: Vec<u8> = (0..8 * 16 * 4).map(|i| i as u8).collect();
+        let format = HardwareTextureFormat::RGBA8;
+        let encoded = encode_pixel_data(&rgba, 8, 16, format).unwrap();
+        assert_eq!(rgba, decode_pixel_data(&encoded, 8, 16, format).unwrap());
+    }
+
+    #[test]
+    fn unsupported_format() {
+        assert!(encode_pixel_data(&[0; 256], 8, 8, HardwareTextureFormat::ETC1).is_err());
+    }
+}
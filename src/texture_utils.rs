@@ -1,4 +1,5 @@
-use crate::TextureDecodeError;
+use crate::texture_decoder::{self, HardwareTextureFormat};
+use crate::{Texture, TextureDecodeError, TextureParseError};
 
 type Result<T> = std::result::Result<T, TextureDecodeError>;
 
@@ -38,17 +39,102 @@ pub fn block_to_sequential(
     Ok(sequential)
 }
 
-pub fn align(value: usize, increment: usize) -> usize {
-    if increment <= 1 {
-        value
-    } else {
-        let tmp = value % increment;
-        if tmp > 0 {
-            value + (increment - tmp)
-        } else {
-            value
+// Inverse of block_to_sequential, for writing formats that store pixels in blocks.
+pub fn sequential_to_block(
+    data: &[u8],
+    texture_width: usize,
+    texture_height: usize,
+    block_width: usize,
+    block_height: usize,
+) -> Result<Vec<u8>> {
+    let block_size = block_width * block_height;
+    let num_blocks_in_row = texture_width / block_width;
+    let num_blocks_in_texture = (texture_width * texture_height) / block_size;
+
+    let mut blocked: Vec<u8> = vec![0; texture_width * texture_height];
+    for block_number in 0..num_blocks_in_texture {
+        let block_row = block_number / num_blocks_in_row;
+        let block_column = block_number % num_blocks_in_row;
+        for block_index in 0..block_size {
+            let row_in_block = block_index / block_width;
+            let column_in_block = block_index % block_width;
+            let index_in_output = block_number * block_size + block_index;
+            let index_in_input = block_row * texture_width * block_height
+                + row_in_block * texture_width
+                + block_column * block_width
+                + column_in_block;
+            if index_in_input < data.len() && index_in_output < blocked.len() {
+                blocked[index_in_output] = data[index_in_input];
+            }
+        }
+    }
+
+    Ok(blocked)
+}
+
+// Splits per-texture results so one bad texture doesn't discard the rest.
+pub fn collect_lenient<I>(results: I) -> (Vec<Texture>, Vec<TextureParseError>)
+where
+    I: IntoIterator<Item = std::result::Result<Texture, TextureParseError>>,
+{
+    let mut textures = Vec::new();
+    let mut errors = Vec::new();
+    for result in results {
+        match result {
+            Ok(texture) => textures.push(texture),
+            Err(err) => errors.push(err),
         }
     }
+    (textures, errors)
+}
+
+// Size of a texture and all of its mip levels.
+pub fn expected_texture_length(
+    width: usize,
+    height: usize,
+    pixel_format: HardwareTextureFormat,
+    mip_levels: usize,
+) -> usize {
+    texture_decoder::get_mip_offset(width, height, pixel_format, mip_levels.max(1))
+}
+
+// A texture whose declared length disagrees with its dimensions.
+// This usually means the reader picked up the wrong width/height/format fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextureLengthMismatch {
+    pub filename: String,
+    pub expected: usize,
+    pub declared: usize,
+}
+
+pub fn check_texture_length(
+    filename: &str,
+    width: usize,
+    height: usize,
+    pixel_format: HardwareTextureFormat,
+    mip_levels: usize,
+    declared_length: usize,
+) -> Option<TextureLengthMismatch> {
+    let expected = expected_texture_length(width, height, pixel_format, mip_levels);
+    if expected == declared_length {
+        None
+    } else {
+        Some(TextureLengthMismatch {
+            filename: filename.to_string(),
+            expected,
+            declared: declared_length,
+        })
+    }
+}
+
+// Inverse of crop. New columns and rows are zero filled.
+pub fn pad(input: &[u8], width: usize, padded_width: usize, padded_height: usize) -> Vec<u8> {
+    let mut output: Vec<u8> = vec![0; padded_width * padded_height];
+    for (r, row) in input.chunks(width).enumerate().take(padded_height) {
+        let base_index = r * padded_width;
+        output[base_index..base_index + row.len()].copy_from_slice(row);
+    }
+    output
 }
 
 pub fn crop(input: &[u8], original_width: usize, width: usize, height: usize) -> Vec<u8> {
@@ -1,12 +1,23 @@
 use std::io::Cursor;
 
-use binread::{BinRead, BinReaderExt, FilePtr32};
+use binread::{BinRead, BinReaderExt, BinResult, FilePtr32, ReadOptions};
+use byteorder::{BigEndian, WriteBytesExt};
+use std::io::{Read, Seek};
 
-use crate::{pixel_encodings::ColorFormat, texture_utils, Texture, TextureParseError};
+use crate::padding::{align, pad_to};
+use crate::{
+    cmpr, pixel_encodings::ColorFormat, texture_utils, Endian, Texture, TextureContainer,
+    TextureDecodeError, TextureParseError, TextureSourceFormat, TextureWriteOptions,
+};
 
 type Result<T> = std::result::Result<T, TextureParseError>;
 
-#[derive(BinRead, Debug, Clone, Copy)]
+// TPL is a GameCube/Wii format, so everything is big endian.
+type FileEndian = BigEndian;
+
+pub const TPL_MAGIC: u32 = 0x0020AF30;
+
+#[derive(BinRead, Debug, Clone, Copy, PartialEq, Eq)]
 #[br(repr = u32)]
 pub enum TplImageFormat {
     I4 = 0,
@@ -31,8 +42,8 @@ pub enum TplPaletteFormat {
 }
 
 #[derive(BinRead)]
-#[br(magic = 0x0020AF30)]
 pub struct Tpl {
+    pub magic: u32,
     pub image_count: u32,
     #[br(parse_with = FilePtr32::parse, count = image_count)]
     pub images: Vec<TplImageTableItem>,
@@ -42,8 +53,24 @@ pub struct Tpl {
 pub struct TplImageTableItem {
     #[br(parse_with = FilePtr32::parse)]
     pub image: TplImage,
-    #[br(parse_with = FilePtr32::parse)]
-    pub palette: TplPalette,
+    #[br(parse_with = parse_optional_palette)]
+    pub palette: Option<TplPalette>,
+}
+
+// Formats without a palette (ex. CMPR) store a null palette pointer.
+fn parse_optional_palette<R: Read + Seek>(
+    reader: &mut R,
+    options: &ReadOptions,
+    args: (),
+) -> BinResult<Option<TplPalette>> {
+    let position = reader.stream_position()?;
+    let offset = u32::read_options(reader, options, args)?;
+    if offset == 0 {
+        Ok(None)
+    } else {
+        reader.seek(std::io::SeekFrom::Start(position))?;
+        Ok(Some(FilePtr32::parse(reader, options, args)?))
+    }
 }
 
 #[derive(BinRead)]
@@ -56,6 +83,31 @@ pub struct TplPalette {
     pub palette_data: Vec<u8>,
 }
 
+// Header-only views of the image table, used to list images without reading pixel data.
+#[allow(dead_code)]
+#[derive(BinRead)]
+struct TplInfoTable {
+    magic: u32,
+    image_count: u32,
+    #[br(parse_with = FilePtr32::parse, count = image_count)]
+    images: Vec<TplInfoTableItem>,
+}
+
+#[allow(dead_code)]
+#[derive(BinRead)]
+struct TplInfoTableItem {
+    #[br(parse_with = FilePtr32::parse)]
+    image: TplImageInfo,
+    palette_offset: u32,
+}
+
+#[derive(BinRead)]
+struct TplImageInfo {
+    height: u16,
+    width: u16,
+    format: TplImageFormat,
+}
+
 #[derive(BinRead)]
 pub struct TplImage {
     pub height: u16,
@@ -76,7 +128,16 @@ pub struct TplImage {
 
 impl Tpl {
     pub fn extract_textures(raw_input: &[u8]) -> Result<Vec<Texture>> {
-        // First, parse the file.
+        Tpl::extract_textures_with_magic(raw_input, &[TPL_MAGIC])
+    }
+
+    // Some third party tools write a different header constant but keep the same layout.
+    pub fn extract_textures_with_magic(
+        raw_input: &[u8],
+        accepted_magic: &[u32],
+    ) -> Result<Vec<Texture>> {
+        // First, validate the magic and parse the file.
+        validate_magic(raw_input, accepted_magic)?;
         let mut cursor = Cursor::new(raw_input);
         let tpl: Tpl = cursor
             .read_be()
@@ -85,44 +146,286 @@ impl Tpl {
         // Decode textures.
         let mut textures: Vec<Texture> = Vec::new();
         for image in &tpl.images {
-            // TODO: Palette is optional
-            // Decode the palette.
-            let palette_format = ColorFormat::from(image.palette.format);
-            let rgba_palette = palette_format.decode(&image.palette.palette_data)?;
-
-            // Decode the image.
             let image_header = &image.image;
-            let image_format = ColorFormat::from(image_header.format);
-            let (block_width, block_height) = image_header.format.block_dimensions();
             let image_width = image_header.width as usize;
             let image_height = image_header.height as usize;
-            let aligned_image_width = texture_utils::align(image_width, block_width);
-            let aligned_image_height = texture_utils::align(image_height, block_height);
+
+            // CMPR is block compressed, so it bypasses the per-pixel path entirely.
+            if let TplImageFormat::CMPR = image_header.format {
+                textures.push(Texture {
+                    filename: String::new(),
+                    height: image_height,
+                    width: image_width,
+                    pixel_data: cmpr::decode(&image_header.image_data, image_width, image_height)?,
+                    raw: image_header.image_data.clone(),
+                    source_format: Some(TextureSourceFormat::Tpl(image_header.format)),
+                });
+                continue;
+            }
+
+            // Decode the image. Block dimensions are in pixels, so scale them to bytes.
+            let image_format = ColorFormat::from(image_header.format);
+            let bytes_per_pixel = image_format.bytes_per_pixel();
+            if bytes_per_pixel == 0 {
+                return Err(TextureDecodeError::UnsupportedFormat.into());
+            }
+            let image_data = if let TplImageFormat::RGBA8 = image_header.format {
+                merge_rgba8_blocks(&image_header.image_data)
+            } else {
+                image_header.image_data.clone()
+            };
+            let (block_width, block_height) = image_header.format.block_dimensions();
+            let aligned_image_width = align(image_width, block_width);
+            let aligned_image_height = align(image_height, block_height);
             let sequential_image_data = texture_utils::block_to_sequential(
-                &image_header.image_data,
-                aligned_image_width,
+                &image_data,
+                aligned_image_width * bytes_per_pixel,
                 aligned_image_height,
-                block_width,
+                block_width * bytes_per_pixel,
                 block_height,
             )?;
             // TODO: Can we get rid of cropping by handling unaligned images in block_to_sequential?
             let cropped_image = texture_utils::crop(
                 &sequential_image_data,
-                aligned_image_width,
-                image_width,
+                aligned_image_width * bytes_per_pixel,
+                image_width * bytes_per_pixel,
                 image_height,
             );
-            let decoded_image_data = image_format.decode_indexed(&cropped_image, &rgba_palette)?;
+            let decoded_image_data = if image_format.is_indexed_format() {
+                let palette = image.palette.as_ref().ok_or_else(|| {
+                    TextureParseError::ParserError("Image is missing a palette.".to_string())
+                })?;
+                let rgba_palette =
+                    ColorFormat::from(palette.format).decode(&palette.palette_data)?;
+                image_format.decode_indexed(&cropped_image, &rgba_palette)?
+            } else {
+                image_format.decode(&cropped_image)?
+            };
             textures.push(Texture {
                 filename: String::new(),
                 height: image_height,
                 width: image_width,
                 pixel_data: decoded_image_data,
+                raw: image_header.image_data.clone(),
+                source_format: Some(TextureSourceFormat::Tpl(image_header.format)),
             });
         }
 
         Ok(textures)
     }
+
+    // Returns (width, height, format) for each image without decoding anything.
+    pub fn image_infos(raw_input: &[u8]) -> Result<Vec<(u16, u16, TplImageFormat)>> {
+        validate_magic(raw_input, &[TPL_MAGIC])?;
+        let mut cursor = Cursor::new(raw_input);
+        let table: TplInfoTable = cursor
+            .read_be()
+            .map_err(|e| TextureParseError::ParserError(format!("{:?}", e)))?;
+        Ok(table
+            .images
+            .iter()
+            .map(|item| (item.image.width, item.image.height, item.image.format))
+            .collect())
+    }
+}
+
+// A texture to write along with its target format and, for indexed formats, an RGBA palette.
+pub type TplWriteEntry = (Texture, TplImageFormat, Option<(TplPaletteFormat, Vec<u8>)>);
+
+const IMAGE_HEADER_SIZE: usize = 0x24;
+const PALETTE_HEADER_SIZE: usize = 0xC;
+
+// Encodes one image, returning its block-tiled data and its encoded palette (if any).
+fn encode_image(entry: &TplWriteEntry) -> Result<(Vec<u8>, Option<Vec<u8>>)> {
+    let (texture, format, palette) = entry;
+    let expected_len = texture.width * texture.height * 4;
+    if texture.pixel_data.len() != expected_len {
+        return Err(TextureDecodeError::BadDimensions {
+            width: texture.width,
+            height: texture.height,
+            expected_len,
+            actual_len: texture.pixel_data.len(),
+        }
+        .into());
+    }
+    let color_format = ColorFormat::from(*format);
+    let (encoded_image, encoded_palette) = match format {
+        TplImageFormat::CI8 => {
+            let (palette_format, rgba_palette) =
+                palette.as_ref().ok_or(TextureDecodeError::NoPalette)?;
+            let encoded_palette = ColorFormat::from(*palette_format).encode(rgba_palette)?;
+            let indices = color_format.encode_indexed(&texture.pixel_data, rgba_palette)?;
+            (indices, Some(encoded_palette))
+        }
+        TplImageFormat::IA8 | TplImageFormat::RGB5A3 | TplImageFormat::RGBA8 => {
+            (color_format.encode(&texture.pixel_data)?, None)
+        }
+        _ => {
+            let name = format!("TPL {:?}", format);
+            return Err(TextureParseError::WriteNotSupported(name));
+        }
+    };
+
+    let bytes_per_pixel = color_format.bytes_per_pixel();
+    let (block_width, block_height) = format.block_dimensions();
+    let aligned_width = align(texture.width, block_width);
+    let aligned_height = align(texture.height, block_height);
+    let padded = texture_utils::pad(
+        &encoded_image,
+        texture.width * bytes_per_pixel,
+        aligned_width * bytes_per_pixel,
+        aligned_height,
+    );
+    let image_data = texture_utils::sequential_to_block(
+        &padded,
+        aligned_width * bytes_per_pixel,
+        aligned_height,
+        block_width * bytes_per_pixel,
+        block_height,
+    )?;
+    if let TplImageFormat::RGBA8 = format {
+        Ok((split_rgba8_blocks(&image_data), encoded_palette))
+    } else {
+        Ok((image_data, encoded_palette))
+    }
+}
+
+// RGBA8 blocks store the AR pairs of all 16 pixels, then their GB pairs.
+// These convert between that and 16 consecutive RGBA pixels.
+fn merge_rgba8_blocks(data: &[u8]) -> Vec<u8> {
+    let mut merged: Vec<u8> = Vec::with_capacity(data.len());
+    for block in data.chunks_exact(64) {
+        for i in 0..16 {
+            let (ar, gb) = (&block[i * 2..i * 2 + 2], &block[32 + i * 2..32 + i * 2 + 2]);
+            merged.extend_from_slice(&[ar[1], gb[0], gb[1], ar[0]]);
+        }
+    }
+    merged
+}
+
+fn split_rgba8_blocks(data: &[u8]) -> Vec<u8> {
+    let mut split: Vec<u8> = Vec::with_capacity(data.len());
+    for block in data.chunks_exact(64) {
+        for pixel in block.chunks_exact(4) {
+            split.extend_from_slice(&[pixel[3], pixel[0]]);
+        }
+        for pixel in block.chunks_exact(4) {
+            split.extend_from_slice(&[pixel[1], pixel[2]]);
+        }
+    }
+    split
+}
+
+pub fn write(textures: &[TplWriteEntry]) -> Result<Vec<u8>> {
+    let encoded = textures
+        .iter()
+        .map(encode_image)
+        .collect::<Result<Vec<_>>>()?;
+
+    // Layout: header, image table, image headers, palette headers, then 32 byte aligned data.
+    let count = textures.len();
+    let image_headers_start = 0xC + count * 8;
+    let palette_headers_start = image_headers_start + count * IMAGE_HEADER_SIZE;
+    let palette_count = encoded.iter().filter(|(_, p)| p.is_some()).count();
+    let data_start = align(
+        palette_headers_start + palette_count * PALETTE_HEADER_SIZE,
+        0x20,
+    );
+
+    let mut data: Vec<u8> = Vec::new();
+    let mut image_offsets: Vec<usize> = Vec::new();
+    let mut palette_offsets: Vec<Option<usize>> = Vec::new();
+    for (image_data, palette_data) in &encoded {
+        palette_offsets.push(palette_data.as_ref().map(|palette_data| {
+            let offset = data_start + data.len();
+            data.extend_from_slice(palette_data);
+            pad_to(&mut data, 0x20);
+            offset
+        }));
+        image_offsets.push(data_start + data.len());
+        data.extend_from_slice(image_data);
+        pad_to(&mut data, 0x20);
+    }
+
+    let mut out: Vec<u8> = Vec::new();
+    out.write_u32::<FileEndian>(TPL_MAGIC)?;
+    out.write_u32::<FileEndian>(count as u32)?;
+    out.write_u32::<FileEndian>(0xC)?;
+    let mut palette_header_address = palette_headers_start;
+    for (i, palette_offset) in palette_offsets.iter().enumerate() {
+        out.write_u32::<FileEndian>((image_headers_start + i * IMAGE_HEADER_SIZE) as u32)?;
+        if palette_offset.is_some() {
+            out.write_u32::<FileEndian>(palette_header_address as u32)?;
+            palette_header_address += PALETTE_HEADER_SIZE;
+        } else {
+            out.write_u32::<FileEndian>(0)?;
+        }
+    }
+    for (i, (texture, format, _)) in textures.iter().enumerate() {
+        out.write_u16::<FileEndian>(texture.height as u16)?;
+        out.write_u16::<FileEndian>(texture.width as u16)?;
+        out.write_u32::<FileEndian>(*format as u32)?;
+        out.write_u32::<FileEndian>(image_offsets[i] as u32)?;
+        out.write_u32::<FileEndian>(0)?; // Wrap S
+        out.write_u32::<FileEndian>(0)?; // Wrap T
+        out.write_u32::<FileEndian>(1)?; // Min filter
+        out.write_u32::<FileEndian>(1)?; // Mag filter
+        out.write_f32::<FileEndian>(0.0)?; // LOD bias
+        out.write_u8(0)?; // Edge LOD enable
+        out.write_u8(0)?; // Min LOD
+        out.write_u8(0)?; // Max LOD
+        out.write_u8(0)?; // Unpacked
+    }
+    for (i, (_, _, palette)) in textures.iter().enumerate() {
+        if let (Some((format, rgba_palette)), Some(offset)) = (palette, palette_offsets[i]) {
+            out.write_u16::<FileEndian>((rgba_palette.len() / 4) as u16)?;
+            out.write_u8(0)?; // Unpacked
+            out.write_u8(0)?; // Padding
+            out.write_u32::<FileEndian>(*format as u32)?;
+            out.write_u32::<FileEndian>(offset as u32)?;
+        }
+    }
+    out.resize(data_start, 0);
+    out.extend_from_slice(&data);
+    Ok(out)
+}
+
+fn validate_magic(raw_input: &[u8], accepted_magic: &[u32]) -> Result<()> {
+    if raw_input.len() < 4 {
+        return Err(TextureParseError::BadMagicNumber);
+    }
+    let magic = Endian::Big
+        .decode_u32(&raw_input[0..4])
+        .map_err(|e| TextureParseError::ParserError(e.to_string()))?;
+    if !accepted_magic.contains(&magic) {
+        return Err(TextureParseError::BadMagicNumber);
+    }
+    Ok(())
+}
+
+impl TextureContainer for Tpl {
+    fn read(bytes: &[u8]) -> Result<Vec<Texture>> {
+        Tpl::extract_textures(bytes)
+    }
+
+    // Textures are written as CI8, indexed against the palette from the options.
+    fn write(textures: &[Texture], options: &TextureWriteOptions) -> Result<Vec<u8>> {
+        let palette = options
+            .palette
+            .as_ref()
+            .ok_or(TextureDecodeError::NoPalette)?;
+        let entries: Vec<TplWriteEntry> = textures
+            .iter()
+            .map(|texture| {
+                (
+                    texture.clone(),
+                    TplImageFormat::CI8,
+                    Some((TplPaletteFormat::RGB5A3, palette.clone())),
+                )
+            })
+            .collect();
+        write(&entries)
+    }
 }
 
 impl TplImageFormat {
@@ -130,8 +433,7 @@ impl TplImageFormat {
         let height = height as usize;
         let width = width as usize;
         let (block_width, block_height) = self.block_dimensions();
-        let base_num_bytes =
-            texture_utils::align(height, block_height) * texture_utils::align(width, block_width);
+        let base_num_bytes = align(height, block_height) * align(width, block_width);
         match self {
             TplImageFormat::I4 => base_num_bytes / 2,
             TplImageFormat::I8 => base_num_bytes,
@@ -143,7 +445,7 @@ impl TplImageFormat {
             TplImageFormat::CI4 => base_num_bytes / 2,
             TplImageFormat::CI8 => base_num_bytes,
             TplImageFormat::CI14X2 => base_num_bytes * 2,
-            TplImageFormat::CMPR => base_num_bytes,
+            TplImageFormat::CMPR => base_num_bytes / 2,
         }
     }
 
@@ -178,6 +480,7 @@ impl TplPaletteFormat {
 impl From<TplPaletteFormat> for ColorFormat {
     fn from(format: TplPaletteFormat) -> Self {
         match format {
+            TplPaletteFormat::IA8 => ColorFormat::IA8,
             TplPaletteFormat::RGB5A3 => ColorFormat::RGB5A3,
             _ => ColorFormat::Unrecognized,
         }
@@ -187,6 +490,7 @@ impl From<TplPaletteFormat> for ColorFormat {
 impl From<TplImageFormat> for ColorFormat {
     fn from(format: TplImageFormat) -> Self {
         match format {
+            TplImageFormat::IA8 => ColorFormat::IA8,
             TplImageFormat::RGB5A3 => ColorFormat::RGB5A3,
             TplImageFormat::RGBA8 => ColorFormat::RGBA8,
             TplImageFormat::CI8 => ColorFormat::CI8,
@@ -194,3 +498,168 @@ impl From<TplImageFormat> for ColorFormat {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build_cmpr_tpl(magic: u32) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend(magic.to_be_bytes().iter());
+        bytes.extend(1u32.to_be_bytes().iter());
+        bytes.extend(0xCu32.to_be_bytes().iter());
+
+        // Image table: image header at 0x14, no palette.
+        bytes.extend(0x14u32.to_be_bytes().iter());
+        bytes.extend(0u32.to_be_bytes().iter());
+
+        // Image header.
+        bytes.extend(8u16.to_be_bytes().iter());
+        bytes.extend(8u16.to_be_bytes().iter());
+        bytes.extend(14u32.to_be_bytes().iter());
+        bytes.extend(0x40u32.to_be_bytes().iter());
+        bytes.resize(0x40, 0);
+
+        // Four opaque white sub-blocks.
+        for _ in 0..4 {
+            bytes.extend(&[0xFF, 0xFF, 0, 0, 0, 0, 0, 0]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn extract_textures_cmpr() {
+        let textures = Tpl::extract_textures(&build_cmpr_tpl(TPL_MAGIC)).unwrap();
+        assert_eq!(1, textures.len());
+        assert_eq!(8, textures[0].width);
+        assert_eq!(8, textures[0].height);
+        assert!(textures[0].pixel_data.iter().all(|b| *b == 0xFF));
+    }
+
+    #[test]
+    fn image_infos_skips_pixel_data() {
+        // Drop the pixel data so a full parse would fail.
+        let mut bytes = build_cmpr_tpl(TPL_MAGIC);
+        bytes.truncate(0x40);
+        assert!(Tpl::extract_textures(&bytes).is_err());
+        let infos = Tpl::image_infos(&bytes).unwrap();
+        assert_eq!(vec![(8, 8, TplImageFormat::CMPR)], infos);
+    }
+
+    #[test]
+    fn write_round_trip() {
+        // Unaligned dimensions so padding and cropping both get exercised.
+        let palette = vec![0xF8, 0x08, 0x40, 0xFF, 0x22, 0x44, 0x66, 0x60];
+        let pixel_data: Vec<u8> = palette.repeat(25);
+        let texture = Texture {
+            width: 10,
+            height: 5,
+            pixel_data: pixel_data.clone(),
+            ..Default::default()
+        };
+        let entry = (
+            texture,
+            TplImageFormat::CI8,
+            Some((TplPaletteFormat::RGB5A3, palette)),
+        );
+        let bytes = write(&[entry]).unwrap();
+        let infos = Tpl::image_infos(&bytes).unwrap();
+        assert_eq!(vec![(10, 5, TplImageFormat::CI8)], infos);
+        let textures = Tpl::extract_textures(&bytes).unwrap();
+        assert_eq!(1, textures.len());
+        assert_eq!(pixel_data, textures[0].pixel_data);
+    }
+
+    fn direct_round_trip(format: TplImageFormat, colors: &[u8]) -> Vec<u8> {
+        // Unaligned dimensions so padding and cropping both get exercised.
+        let pixel_data: Vec<u8> = colors.repeat(50 * 4 / colors.len());
+        let texture = Texture {
+            width: 10,
+            height: 5,
+            pixel_data: pixel_data.clone(),
+            ..Default::default()
+        };
+        let bytes = write(&[(texture, format, None)]).unwrap();
+        assert_eq!(vec![(10, 5, format)], Tpl::image_infos(&bytes).unwrap());
+        let textures = Tpl::extract_textures(&bytes).unwrap();
+        assert_eq!(1, textures.len());
+        assert_eq!(pixel_data, textures[0].pixel_data);
+        bytes
+    }
+
+    #[test]
+    fn write_round_trip_ia8() {
+        direct_round_trip(
+            TplImageFormat::IA8,
+            &[0x40, 0x40, 0x40, 0x80, 0xFF, 0xFF, 0xFF, 0x00],
+        );
+    }
+
+    #[test]
+    fn write_round_trip_rgb5a3() {
+        direct_round_trip(
+            TplImageFormat::RGB5A3,
+            &[0xF8, 0x08, 0x40, 0xFF, 0x22, 0x44, 0x66, 0x60],
+        );
+    }
+
+    #[test]
+    fn write_round_trip_rgba8() {
+        let bytes = direct_round_trip(
+            TplImageFormat::RGBA8,
+            &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88],
+        );
+        // The first block holds AR pairs, then GB pairs.
+        assert_eq!(&[0x44, 0x11, 0x88, 0x55], &bytes[0x40..0x44]);
+        assert_eq!(&[0x22, 0x33, 0x66, 0x77], &bytes[0x60..0x64]);
+    }
+
+    #[test]
+    fn write_through_texture_container() {
+        let palette = vec![0xF8, 0x08, 0x40, 0xFF, 0x22, 0x44, 0x66, 0x60];
+        let texture = Texture {
+            width: 8,
+            height: 4,
+            pixel_data: palette.repeat(16),
+            ..Default::default()
+        };
+        let textures = std::slice::from_ref(&texture);
+        assert!(matches!(
+            <Tpl as TextureContainer>::write(textures, &TextureWriteOptions::default()),
+            Err(TextureParseError::TextureDecodeError(
+                TextureDecodeError::NoPalette
+            ))
+        ));
+        let options = TextureWriteOptions {
+            palette: Some(palette),
+            ..Default::default()
+        };
+        let bytes = <Tpl as TextureContainer>::write(textures, &options).unwrap();
+        let read_back = Tpl::extract_textures(&bytes).unwrap();
+        assert_eq!(texture.pixel_data, read_back[0].pixel_data);
+    }
+
+    #[test]
+    fn write_unsupported() {
+        let texture = Texture {
+            width: 8,
+            height: 8,
+            pixel_data: vec![0; 8 * 8 * 4],
+            ..Default::default()
+        };
+        match write(&[(texture, TplImageFormat::CMPR, None)]) {
+            Err(TextureParseError::WriteNotSupported(_)) => {}
+            _ => panic!("Expected WriteNotSupported."),
+        }
+    }
+
+    #[test]
+    fn extract_textures_unknown_magic() {
+        let bytes = build_cmpr_tpl(0x12345678);
+        assert!(matches!(
+            Tpl::extract_textures(&bytes),
+            Err(TextureParseError::BadMagicNumber)
+        ));
+        assert!(Tpl::extract_textures_with_magic(&bytes, &[TPL_MAGIC, 0x12345678]).is_ok());
+    }
+}